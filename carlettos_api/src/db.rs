@@ -1,11 +1,16 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use carlettos_chess::chess_controller::CChess;
 use chess_api::Board;
 use chrono::{DateTime, Utc};
+use rocket::FromFormField;
 use serde::{Deserialize, Serialize};
 use surrealdb::engine::any::Any;
 use surrealdb::opt::auth::Root;
 use surrealdb::opt::PatchOp;
+use surrealdb::sql::Datetime;
 use surrealdb::sql::Object;
 use surrealdb::sql::Thing;
 use surrealdb::sql::Value;
@@ -13,11 +18,51 @@ use surrealdb::Surreal;
 
 use crate::prelude;
 use crate::prelude::IdBoard;
+use crate::prelude::IdCChess;
 use crate::prelude::LeaderboardEntry;
 use crate::prelude::ThingVotes;
 use crate::prelude::Vote;
 use crate::utils::macros::map;
 
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    FromFormField,
+)]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Low" => Some(Priority::Low),
+            "Medium" => Some(Priority::Medium),
+            "High" => Some(Priority::High),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Task {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -26,6 +71,10 @@ pub struct Task {
     pub completed: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub priority: Priority,
 }
 
 impl Task {
@@ -59,8 +108,30 @@ impl Task {
                     return None;
                 }
             }),
+            due_at: match obj.get("due_at") {
+                Some(Value::Datetime(dt)) => Some(dt.0),
+                Some(Value::None | Value::Null) | None => None,
+                Some(_) => return None,
+            },
+            priority: match obj.get("priority") {
+                Some(Value::Strand(name)) => Priority::from_name(&name.0)?,
+                Some(Value::None | Value::Null) | None => Priority::default(),
+                Some(_) => return None,
+            },
         })
     }
+
+    /// Higher priorities first, then the closest due dates, tasks without one go last.
+    pub fn sort(tasks: &mut [Task]) {
+        tasks.sort_by_key(|task| {
+            (
+                Reverse(task.priority),
+                task.due_at.is_none(),
+                task.due_at,
+                task.created_at,
+            )
+        });
+    }
 }
 
 impl From<Object> for Task {
@@ -71,19 +142,21 @@ impl From<Object> for Task {
 
 impl From<Task> for Value {
     fn from(task: Task) -> Self {
-        match task.id {
-            Some(t) => map![
-                "id".into() => t.into(),
-                "title".into() => task.title.into(),
-                "completed".into() => task.completed.into()
-            ]
-            .into(),
-            None => map![
-                "title".into() => task.title.into(),
-                "completed".into() => task.completed.into()
-            ]
-            .into(),
+        let mut map: BTreeMap<String, Value> = map![
+            "title".into() => task.title.into(),
+            "completed".into() => task.completed.into(),
+            "priority".into() => task.priority.as_str().into()
+        ];
+        if let Some(t) = task.id {
+            map.insert("id".into(), t.into());
+        }
+        if let Some(created_at) = task.created_at {
+            map.insert("created_at".into(), Datetime::from(created_at).into());
+        }
+        if let Some(due_at) = task.due_at {
+            map.insert("due_at".into(), Datetime::from(due_at).into());
         }
+        map.into()
     }
 }
 
@@ -123,13 +196,20 @@ impl DB {
         Ok(())
     }
 
-    pub async fn add_task(&self, title: String) -> Result<Object, prelude::Error> {
+    pub async fn add_task(
+        &self,
+        title: String,
+        due_at: Option<DateTime<Utc>>,
+        priority: Priority,
+    ) -> Result<Object, prelude::Error> {
         self.connect().await?;
-        let query = "CREATE tasks SET title = $title, completed = false, created_at = time::now();";
+        let query = "CREATE tasks SET title = $title, completed = false, created_at = time::now(), due_at = $due_at, priority = $priority;";
         let result = self
             .db
             .query(query)
             .bind(("title", title))
+            .bind(("due_at", due_at.map(Datetime::from)))
+            .bind(("priority", priority.as_str()))
             .await?
             .take::<Value>(0)?;
 
@@ -164,6 +244,22 @@ impl DB {
         }
     }
 
+    pub async fn create_cchess_game(&self) -> Result<IdCChess, prelude::Error> {
+        self.connect().await?;
+        if let Some(game) = self.db.select(("cchess", 0)).await? {
+            Ok(game)
+        } else {
+            let game = IdCChess::new(CChess::cchessboard(), Utc::now());
+            if let Some(game) = self.db.create(("cchess", 0)).content(game).await? {
+                Ok(game)
+            } else {
+                Err(prelude::Error::ValueNotFound(
+                    "Couldn't create carlettos chess game".to_string(),
+                ))
+            }
+        }
+    }
+
     pub async fn get_task(&self, id: String) -> Result<Task, prelude::Error> {
         self.connect().await?;
         if let Some(task) = self.db.select(("tasks", &id)).await? {
@@ -195,12 +291,34 @@ impl DB {
         }
     }
 
+    pub async fn get_cchess_game(&self) -> Result<IdCChess, prelude::Error> {
+        self.connect().await?;
+        if let Some(game) = self.db.select(("cchess", 0)).await? {
+            Ok(game)
+        } else {
+            Err(prelude::Error::ValueNotFound(
+                "Carlettos chess game not found".to_string(),
+            ))
+        }
+    }
+
+    pub async fn update_cchess_game(&self, game: IdCChess) -> Result<IdCChess, prelude::Error> {
+        self.connect().await?;
+        if let Some(game) = self.db.update(("cchess", 0)).content(game).await? {
+            Ok(game)
+        } else {
+            Err(prelude::Error::ValueNotFound(
+                "Carlettos chess game cannot be updated".to_string(),
+            ))
+        }
+    }
+
     pub async fn get_all_tasks(&self) -> Result<Vec<Task>, prelude::Error> {
         self.connect().await?;
         let a: Result<Vec<Task>, surrealdb::Error> = self.db.select("tasks").await;
         println!("{:?}", a);
         let mut tasks: Vec<Task> = a?;
-        tasks.sort_by_key(|task| task.created_at.unwrap());
+        Task::sort(&mut tasks);
         Ok(tasks)
     }
 
@@ -220,6 +338,25 @@ impl DB {
         }
     }
 
+    pub async fn rename_task(
+        &self,
+        id: String,
+        title: String,
+    ) -> Result<AffectedRows, prelude::Error> {
+        self.connect().await?;
+        if self
+            .db
+            .update::<Option<Task>>(("tasks", &id))
+            .patch(PatchOp::replace("title", title))
+            .await?
+            .is_some()
+        {
+            Ok(AffectedRows { rows_affected: 1 })
+        } else {
+            Err(prelude::Error::ValueNotFound(id))
+        }
+    }
+
     pub async fn delete_task(&self, id: String) -> Result<AffectedRows, prelude::Error> {
         self.connect().await?;
         let _: Option<Task> = self.db.delete(("tasks", id)).await?;
@@ -227,6 +364,16 @@ impl DB {
         Ok(AffectedRows { rows_affected: 1 })
     }
 
+    pub async fn clear_completed(&self) -> Result<AffectedRows, prelude::Error> {
+        self.connect().await?;
+        let query = "DELETE tasks WHERE completed = true RETURN BEFORE;";
+        let deleted = self.db.query(query).await?.take::<Vec<Task>>(0)?;
+
+        Ok(AffectedRows {
+            rows_affected: deleted.len() as u64,
+        })
+    }
+
     pub async fn get_votes(&self, id: String) -> Result<ThingVotes, prelude::Error> {
         self.connect().await?;
         if let Some(votes) = self.db.select(("vote", id.clone())).await? {
@@ -314,3 +461,84 @@ impl DB {
         Ok(hs.remove(0))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn task(title: &str, priority: Priority, due_at: Option<DateTime<Utc>>) -> Task {
+        Task {
+            id: Some(Thing::from(("tasks", title))),
+            title: title.to_string(),
+            completed: false,
+            created_at: Some(Utc::now()),
+            due_at,
+            priority,
+        }
+    }
+
+    #[test]
+    fn due_date_round_trip() {
+        let due_at = "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let original = task("homework", Priority::High, Some(due_at));
+        let created_at = original.created_at;
+
+        let Value::Object(obj) = Value::from(original) else {
+            panic!("a task must become an object");
+        };
+        let task = Task::from_obj(obj).unwrap();
+        assert_eq!(task.title, "homework");
+        assert_eq!(task.due_at, Some(due_at));
+        assert_eq!(task.created_at, created_at);
+        assert_eq!(task.priority, Priority::High);
+    }
+
+    #[test]
+    fn sort_by_priority_then_due_date() {
+        let soon = "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let later = "2024-06-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut tasks = vec![
+            task("low", Priority::Low, Some(soon)),
+            task("undated", Priority::High, None),
+            task("later", Priority::High, Some(later)),
+            task("soon", Priority::High, Some(soon)),
+        ];
+        Task::sort(&mut tasks);
+        let titles: Vec<_> = tasks.iter().map(|task| task.title.as_str()).collect();
+        assert_eq!(titles, ["soon", "later", "undated", "low"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the SurrealDB server of DB_IP, see mprocs.yaml"]
+    async fn clear_completed_counts_the_deleted_tasks() {
+        let db = surrealdb::engine::any::connect(std::env::var("DB_IP").unwrap())
+            .await
+            .unwrap();
+        let db = DB { db: Arc::new(db) };
+        db.root_signin().await.unwrap();
+        db.db.use_ns("test").use_db("tasks").await.unwrap();
+        let _: Vec<Task> = db.db.delete("tasks").await.unwrap();
+
+        let mut ids = Vec::new();
+        for title in ["milk", "eggs", "bread"] {
+            let obj = db
+                .add_task(title.to_string(), None, Priority::default())
+                .await
+                .unwrap();
+            ids.push(Task::from_obj(obj).unwrap().id.unwrap().id.to_raw());
+        }
+        db.toggle_task(ids[0].clone()).await.unwrap();
+        db.toggle_task(ids[2].clone()).await.unwrap();
+
+        assert_eq!(db.clear_completed().await.unwrap().rows_affected, 2);
+        let titles: Vec<_> = db
+            .get_all_tasks()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| task.title)
+            .collect();
+        assert_eq!(titles, ["eggs"]);
+        assert_eq!(db.clear_completed().await.unwrap().rows_affected, 0);
+    }
+}
@@ -1,11 +1,13 @@
 #[macro_use]
 extern crate rocket;
 
+use carlettos_chess::{board::PublicBoard, Color, Info, Pos};
 use chess_api::Board;
+use chrono::{DateTime, Utc};
 use cors::CORS;
-use db::{AffectedRows, Task, DB};
-use prelude::{LeaderboardEntry, Votes};
-use rocket::{serde::json::Json, State};
+use db::{AffectedRows, Priority, Task, DB};
+use prelude::{parse_color, LeaderboardEntry, Votes};
+use rocket::{http::Status, serde::json::Json, State};
 
 use std::{
     io::{self, ErrorKind},
@@ -21,10 +23,19 @@ pub mod utils {
 pub mod cors;
 pub mod db;
 
-#[post("/task/<title>")]
-async fn add_task(title: String, db: &State<DB>) -> Result<Json<Task>, io::Error> {
+#[post("/task/<title>?<due_at>&<priority>")]
+async fn add_task(
+    title: String,
+    due_at: Option<&str>,
+    priority: Option<Priority>,
+    db: &State<DB>,
+) -> Result<Json<Task>, io::Error> {
+    let due_at = due_at
+        .map(|due_at| due_at.parse::<DateTime<Utc>>())
+        .transpose()
+        .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "Invalid due date"))?;
     let task = db
-        .add_task(title)
+        .add_task(title, due_at, priority.unwrap_or_default())
         .await
         .map_err(|_| io::Error::new(ErrorKind::Other, "Unable to create task"))?;
     Ok(Json(task.into()))
@@ -57,6 +68,19 @@ async fn toggle_task(id: String, db: &State<DB>) -> Result<Json<AffectedRows>, i
     Ok(Json(affected_rows))
 }
 
+#[patch("/task/<id>/title/<title>")]
+async fn rename_task(
+    id: String,
+    title: String,
+    db: &State<DB>,
+) -> Result<Json<AffectedRows>, io::Error> {
+    let affected_rows = db
+        .rename_task(id, title)
+        .await
+        .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    Ok(Json(affected_rows))
+}
+
 #[delete("/task/<id>")]
 async fn delete_task(id: String, db: &State<DB>) -> Result<Json<AffectedRows>, io::Error> {
     let affected_rows = db
@@ -66,6 +90,15 @@ async fn delete_task(id: String, db: &State<DB>) -> Result<Json<AffectedRows>, i
     Ok(Json(affected_rows))
 }
 
+#[delete("/tasks/completed")]
+async fn clear_completed(db: &State<DB>) -> Result<Json<AffectedRows>, io::Error> {
+    let affected_rows = db
+        .clear_completed()
+        .await
+        .map_err(|_| io::Error::new(ErrorKind::Other, "Unable to clear completed tasks"))?;
+    Ok(Json(affected_rows))
+}
+
 #[get("/chess")]
 async fn get_chess_game(db: &State<DB>) -> Result<Json<Board>, io::Error> {
     let board = db
@@ -84,6 +117,70 @@ async fn update_chess_game(json: String, db: &State<DB>) -> Result<Json<Board>,
     Ok(Json(board.board))
 }
 
+/// What the player of `color`, or a spectator without one, sees of the hosted carlettos chess
+/// game, passing the movements its players let time out.
+#[get("/cchess?<color>")]
+async fn get_cchess_game(
+    color: Option<&str>,
+    db: &State<DB>,
+) -> Result<Json<PublicBoard>, io::Error> {
+    let color = cchess_color(color)?;
+    let mut game = db
+        .get_cchess_game()
+        .await
+        .map_err(|_| io::Error::new(ErrorKind::Other, "Unable to get carlettos chess game"))?;
+    if game.pass_idle_movements(Utc::now()) > 0 {
+        game = db.update_cchess_game(game).await.map_err(|_| {
+            io::Error::new(ErrorKind::Other, "Unable to update carlettos chess game")
+        })?;
+    }
+    Ok(Json(game.view(color.as_ref())))
+}
+
+#[patch("/cchess/click/<x>/<y>?<color>")]
+async fn click_cchess_game(
+    x: usize,
+    y: usize,
+    color: Option<&str>,
+    db: &State<DB>,
+) -> Result<Json<PublicBoard>, io::Error> {
+    let color = cchess_color(color)?;
+    let mut game = db
+        .get_cchess_game()
+        .await
+        .map_err(|_| io::Error::new(ErrorKind::Other, "Unable to get carlettos chess game"))?;
+    let now = Utc::now();
+    game.pass_idle_movements(now);
+    game.click(Pos::new(x, y), now);
+    let game = db
+        .update_cchess_game(game)
+        .await
+        .map_err(|_| io::Error::new(ErrorKind::Other, "Unable to update carlettos chess game"))?;
+    Ok(Json(game.view(color.as_ref())))
+}
+
+/// The infos the piece at `x`, `y` of the hosted carlettos chess game can use its ability with.
+#[get("/cchess/abilities/<x>/<y>")]
+async fn get_cchess_abilities(
+    x: usize,
+    y: usize,
+    db: &State<DB>,
+) -> Result<Json<Vec<Info>>, io::Error> {
+    let game = db
+        .get_cchess_game()
+        .await
+        .map_err(|_| io::Error::new(ErrorKind::Other, "Unable to get carlettos chess game"))?;
+    Ok(Json(game.ability_infos(&Pos::new(x, y))))
+}
+
+fn cchess_color(color: Option<&str>) -> Result<Option<Color>, io::Error> {
+    color
+        .map(|color| {
+            parse_color(color).ok_or(io::Error::new(ErrorKind::InvalidInput, "Invalid color"))
+        })
+        .transpose()
+}
+
 #[get("/votes/<id>")]
 async fn get_votes(id: String, db: &State<DB>) -> Result<Json<Votes>, io::Error> {
     let votes = db.get_votes(id).await.map_err(io::Error::other)?;
@@ -113,11 +210,14 @@ async fn add_highscore(
     avg_energy: isize,
     max_energy: isize,
     db: &State<DB>,
-) -> Result<Json<LeaderboardEntry>, io::Error> {
+) -> Result<Json<LeaderboardEntry>, Status> {
+    if !LeaderboardEntry::is_valid_name(&name) {
+        return Err(Status::BadRequest);
+    }
     let highscore = db
         .add_highscore(name, score, max_tile, avg_energy, max_energy)
         .await
-        .map_err(io::Error::other)?;
+        .map_err(|_| Status::InternalServerError)?;
     Ok(Json(highscore))
 }
 
@@ -149,6 +249,7 @@ async fn rocket() -> _ {
 
     // this should create a game if not exist, if exist, will do nothing
     db.create_chess_game().await.unwrap();
+    db.create_cchess_game().await.unwrap();
 
     rocket::build()
         .mount(
@@ -158,9 +259,14 @@ async fn rocket() -> _ {
                 get_task,
                 get_all_tasks,
                 toggle_task,
+                rename_task,
                 delete_task,
+                clear_completed,
                 get_chess_game,
                 update_chess_game,
+                get_cchess_game,
+                click_cchess_game,
+                get_cchess_abilities,
                 get_votes,
                 add_vote,
                 remove_vote,
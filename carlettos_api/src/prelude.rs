@@ -1,4 +1,6 @@
+use carlettos_chess::{board::PublicBoard, chess_controller::CChess, Action, Color, Info, Pos};
 use chess_api::Board;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Thing;
 
@@ -23,6 +25,82 @@ impl From<Board> for IdBoard {
     }
 }
 
+/// How long a player of the hosted carlettos chess game may take for a movement before it
+/// is passed for them.
+pub fn turn_timeout() -> Duration {
+    Duration::minutes(5)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IdCChess {
+    pub id: Thing,
+    pub game: CChess,
+    /// When the last movement was made or passed.
+    pub last_move_at: DateTime<Utc>,
+}
+
+impl IdCChess {
+    pub fn new(game: CChess, now: DateTime<Utc>) -> Self {
+        IdCChess {
+            id: Thing {
+                tb: "cchess".to_string(),
+                id: surrealdb::sql::Id::Number(0),
+            },
+            game,
+            last_move_at: now,
+        }
+    }
+
+    /// Passes a movement for every `turn_timeout` the players let go by since the last one,
+    /// return how many were passed.
+    pub fn pass_idle_movements(&mut self, now: DateTime<Utc>) -> usize {
+        let mut passed = 0;
+        while now - self.last_move_at >= turn_timeout() && self.game.skip_movement() {
+            self.last_move_at += turn_timeout();
+            passed += 1;
+        }
+        passed
+    }
+
+    /// Clicks the board, restarting the timeout if the click made a movement.
+    pub fn click(&mut self, pos: Pos, now: DateTime<Utc>) {
+        let time = self.game.board.time.clone();
+        self.game.click(pos);
+        if self.game.board.time != time {
+            self.last_move_at = now;
+        }
+    }
+
+    /// What the player of `color` can see of the game, or a spectator without a color.
+    pub fn view(&self, color: Option<&Color>) -> PublicBoard {
+        match color {
+            Some(color) => self.game.board.view_for(color),
+            None => self.game.board.public_view(),
+        }
+    }
+
+    /// The infos the piece at `pos` can use its ability with, see `CChess::ability_targets`.
+    pub fn ability_infos(&self, pos: &Pos) -> Vec<Info> {
+        self.game
+            .ability_targets(pos)
+            .into_iter()
+            .filter_map(|action| match action {
+                Action::Ability { info, .. } => Some(info),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Parses the `color` query of the carlettos chess routes.
+pub fn parse_color(name: &str) -> Option<Color> {
+    match name {
+        "white" => Some(Color::White),
+        "black" => Some(Color::Black),
+        _ => None,
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Vote {
     pub id: usize,
@@ -70,3 +148,87 @@ pub struct LeaderboardEntry {
     pub avg_energy: isize,
     pub max_energy: isize,
 }
+
+impl LeaderboardEntry {
+    pub const MAX_NAME_LEN: usize = 16;
+
+    /// A name must be trimmed, non empty, of at most `MAX_NAME_LEN` printable characters.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.trim() == name
+            && name.chars().count() <= Self::MAX_NAME_LEN
+            && !name.chars().any(char::is_control)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use carlettos_chess::{card::Card, piece::Piece};
+
+    #[test]
+    fn leaderboard_name_validation() {
+        assert!(LeaderboardEntry::is_valid_name("carlettos"));
+        assert!(LeaderboardEntry::is_valid_name("ñandú 2048"));
+        assert!(!LeaderboardEntry::is_valid_name(
+            "a very long name for a leaderboard"
+        ));
+        assert!(!LeaderboardEntry::is_valid_name(" padded "));
+        assert!(!LeaderboardEntry::is_valid_name("new\nline"));
+        assert!(!LeaderboardEntry::is_valid_name(""));
+    }
+
+    #[test]
+    fn idle_games_pass_on_the_next_access() {
+        let start = "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut game = IdCChess::new(CChess::default_chessboard(), start);
+        let first = game.game.board.current_player().color().clone();
+
+        assert_eq!(game.pass_idle_movements(start + Duration::minutes(4)), 0);
+        assert_eq!(game.game.board.current_player().color(), &first);
+
+        let later = start + turn_timeout() + Duration::seconds(30);
+        assert_eq!(game.pass_idle_movements(later), 1);
+        assert_ne!(game.game.board.current_player().color(), &first);
+        assert_eq!(game.last_move_at, start + turn_timeout());
+        // the next player's time started when the movement was passed
+        assert_eq!(game.pass_idle_movements(later), 0);
+    }
+
+    #[test]
+    fn white_cant_see_blacks_hand() {
+        let mut game = IdCChess::new(CChess::cchessboard(), Utc::now());
+        game.game
+            .board
+            .mut_player_from_color(&Color::Black)
+            .unwrap()
+            .hand
+            .add(Card::Revive);
+        assert_eq!(parse_color("purple"), None);
+
+        let white = serde_json::to_string(&game.view(parse_color("white").as_ref())).unwrap();
+        assert!(white.contains("AddMovement"));
+        assert!(!white.contains("Revive"));
+
+        let black = serde_json::to_string(&game.view(parse_color("black").as_ref())).unwrap();
+        assert!(black.contains("Revive"));
+
+        let spectator = serde_json::to_string(&game.view(None)).unwrap();
+        assert!(!spectator.contains("AddMovement"));
+        assert!(!spectator.contains("Revive"));
+    }
+
+    #[test]
+    fn bishop_abilities_are_its_directions() {
+        let mut game = IdCChess::new(CChess::default(), Utc::now());
+        let bishop = Pos::new(3, 3);
+        game.game.place(bishop.clone(), Piece::bishop(Color::White));
+        let infos = game.ability_infos(&bishop);
+        assert_eq!(infos.len(), 4);
+        assert!(infos.iter().all(|info| matches!(info, Info::Direction(_))));
+        assert!(game.ability_infos(&Pos::new(0, 0)).is_empty());
+
+        let json = serde_json::to_string(&infos).unwrap();
+        assert_eq!(json.matches("\"Direction\"").count(), 4);
+    }
+}
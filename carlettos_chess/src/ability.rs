@@ -1,5 +1,7 @@
 use core::panic;
 
+use serde::Serialize;
+
 use crate::{
     board::{Board, Event, EventFunction, FilterFunction, Mana, Tile},
     card::Card,
@@ -11,6 +13,16 @@ use crate::{
 pub struct AbilityData {
     pub cooldown: Time,
     pub cost: Mana,
+    pub description: &'static str,
+}
+
+/// What a piece's ability does and costs, for tooltips, see `Piece::ability_description`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AbilityInfo {
+    pub name: &'static str,
+    pub cost: Mana,
+    pub cooldown: Time,
+    pub description: &'static str,
 }
 
 pub trait Ability {
@@ -26,6 +38,7 @@ impl Ability for Pawn {
         AbilityData {
             cooldown: Time::default(),
             cost: Mana::default(),
+            description: "Promotes into the given piece when it reaches the last row.",
         }
     }
 
@@ -52,6 +65,7 @@ impl Ability for Knight {
         AbilityData {
             cooldown: Time::rounds(10),
             cost: Mana(1),
+            description: "Places an allied pawn on each side, if both tiles are empty.",
         }
     }
 
@@ -83,6 +97,7 @@ impl Ability for Bishop {
         AbilityData {
             cooldown: Time::rounds(2),
             cost: Mana(0),
+            description: "Moves one tile in the given direction, if it is empty.",
         }
     }
 
@@ -117,6 +132,7 @@ impl Ability for Rook {
         AbilityData {
             cooldown: Time::rounds(10),
             cost: Mana(0),
+            description: "Throws every rook connected to it in the given direction, until they hit a piece or the edge.",
         }
     }
 
@@ -188,6 +204,7 @@ impl Ability for Queen {
         AbilityData {
             cooldown: Time::rounds(5),
             cost: Mana(0),
+            description: "Jumps like a knight to an empty tile.",
         }
     }
     fn r#use(board: &mut Board, from: &Pos, info: Info) {
@@ -211,6 +228,7 @@ impl Ability for King {
         AbilityData {
             cooldown: Time::default(),
             cost: Mana(2),
+            description: "Teleports to any empty tile up to 5 tiles away.",
         }
     }
 
@@ -235,11 +253,19 @@ impl Ability for Builder {
         AbilityData {
             cooldown: Time::rounds(10),
             cost: Mana(0),
+            description: "Builds walls on the empty buildable tiles towards the given direction.",
         }
     }
 
-    fn can_use(_board: &Board, _from: &Pos, info: &Info) -> bool {
-        matches!(info, Info::Direction(_))
+    fn can_use(board: &Board, from: &Pos, info: &Info) -> bool {
+        match info {
+            Info::Direction(dir) => dir.related_subdirections().iter().any(|subdir| {
+                from.subdirection_shift(subdir)
+                    .and_then(|pos| board.get(&pos))
+                    .is_some_and(|tile| tile.is_empty() && tile.buildable)
+            }),
+            _ => false,
+        }
     }
 
     fn r#use(board: &mut Board, from: &Pos, info: Info) {
@@ -248,7 +274,7 @@ impl Ability for Builder {
             for subdir in dir.related_subdirections() {
                 if let Some(pos) = from.subdirection_shift(&subdir) {
                     if let Some(tile) = board.get_mut(&pos) {
-                        if tile.is_empty() {
+                        if tile.is_empty() && tile.buildable {
                             tile.replace(Piece::wall(color.clone()));
                         }
                     }
@@ -267,6 +293,8 @@ impl Ability for Catapult {
         AbilityData {
             cooldown: Time::rounds(2),
             cost: Mana(0),
+            description:
+                "Throws an adjacent transportable piece to an empty tile in the given direction.",
         }
     }
 
@@ -277,8 +305,7 @@ impl Ability for Catapult {
                     (Info::Direction(dir), Info::SubDirection(subdir), Info::Integer(squares)) => {
                         if let Some(piece_pos) = from.subdirection_shift(subdir) {
                             if let Some(tile) = board.get(&piece_pos) {
-                                if tile.piece.is_transportable(&5) {
-                                    // TODO: add an attribute to make this dynamic
+                                if board.can_transport(from, tile.pos()) {
                                     let (x, y): (isize, isize) = (dir).into();
                                     let to =
                                         from.shift(x * *squares as isize, y * *squares as isize);
@@ -330,6 +357,7 @@ impl Ability for CrazyPawn {
         AbilityData {
             cooldown: Time::default(),
             cost: Mana(0),
+            description: "Its player takes two cards and shuffles the deck.",
         }
     }
 
@@ -357,6 +385,7 @@ impl Ability for Magician {
         AbilityData {
             cooldown: Time::rounds(6),
             cost: Mana(2),
+            description: "Burns or freezes every piece up to 4 tiles away, with the Fire or Ice card on the board.",
         }
     }
 
@@ -389,6 +418,7 @@ impl Ability for Paladin {
         AbilityData {
             cooldown: Time::rounds(8),
             cost: Mana(2),
+            description: "With the right card on the board, attacks an enemy even through the immunity of demonic ones, makes an ally invulnerable for 5 rounds, dispels the fire, ice and deactivation of an ally, or revives the last dead ally.",
         }
     }
 
@@ -397,14 +427,23 @@ impl Ability for Paladin {
             Info::PaladinAbilityInfo(ability_type) => match ability_type {
                 PaladinAbilityType::Attack(to) => {
                     board.has_card_on_board(Card::AttackDemonic)
-                        && !board.is_empty(to)
                         && !board.same_color(from, to)
+                        && if Paladin::goes_through_immunity(board, to) {
+                            board.can_attack_piece(from, to)
+                        } else {
+                            board.can_ability_attack_piece(from, to)
+                        }
                 }
                 PaladinAbilityType::Invulnerability(to) => {
                     board.has_card_on_board(Card::Invulnerability)
                         && !board.is_empty(to)
                         && board.same_color(from, to)
                 }
+                PaladinAbilityType::Dispel(to) => {
+                    board.has_card_on_board(Card::Dispel)
+                        && !board.is_empty(to)
+                        && board.same_color(from, to)
+                }
                 PaladinAbilityType::Revive(to) => {
                     board.has_card_on_board(Card::Revive) && board.is_empty(to)
                 }
@@ -416,15 +455,22 @@ impl Ability for Paladin {
     fn r#use(board: &mut Board, from: &Pos, info: Info) {
         if let Info::PaladinAbilityInfo(ability_type) = info {
             match ability_type {
-                PaladinAbilityType::Attack(to) => board.attack_piece(from, &to),
+                PaladinAbilityType::Attack(to) => {
+                    if Paladin::goes_through_immunity(board, &to) {
+                        board.attack_piece(from, &to);
+                    } else {
+                        board.ability_attack_piece(from, &to);
+                    }
+                }
                 PaladinAbilityType::Invulnerability(to) => board
                     .get_mut_data(&to)
                     .unwrap()
                     .add_effect(Effect::Invulnerability(Time::rounds(5))),
+                PaladinAbilityType::Dispel(to) => board.get_mut_data(&to).unwrap().dispel(),
                 PaladinAbilityType::Revive(to) => {
                     let self_color = board.get_data(from).unwrap().color.clone();
-                    let revived_piece = board.remove_last_dead_with_color(&self_color);
-                    board.get_mut(&to).unwrap().replace(revived_piece);
+                    // nothing is revived if no piece of its color has died
+                    let _ = board.resurrect(&self_color, &to);
                 }
             }
         } else {
@@ -433,6 +479,14 @@ impl Ability for Paladin {
     }
 }
 
+impl Paladin {
+    /// The attack kills like any other ability, sparing the immune pieces, except the demonic
+    /// ones: the `AttackDemonic` card is made against them, so it goes through their immunity.
+    fn goes_through_immunity(board: &Board, to: &Pos) -> bool {
+        board.get_piece(to).is_some_and(Piece::is_demonic)
+    }
+}
+
 pub struct Ram;
 
 impl Ability for Ram {
@@ -440,6 +494,7 @@ impl Ability for Ram {
         AbilityData {
             cooldown: Time::turns(4),
             cost: Mana(0),
+            description: "Charges in the given direction, taking the pieces in its way until it hits an impenetrable one.",
         }
     }
 
@@ -456,28 +511,28 @@ impl Ability for Ram {
 
             if let Some(ref collision) = raycast.collision {
                 let charge = raycast.len() / 5 + 1;
-                // if collision is impenetrable, just stay in front of that piece
-                // either, kill that piece and continue until charge is 0 or the next piece is impenetrable
-                if board
-                    .get(collision)
-                    .unwrap()
-                    .piece
-                    .is_impenetrable(strength)
-                {
+                // if collision is impenetrable or can't be attacked, just stay in front of that piece
+                // either, kill that piece and continue until charge is 0 or the next piece stops it
+                let stops = |board: &Board, to: &Pos| {
+                    let piece = &board.get(to).unwrap().piece;
+                    piece.is_impenetrable(strength)
+                        || (piece.data().is_some() && !board.can_ability_attack_piece(from, to))
+                };
+                if stops(board, collision) {
                     let ram = board.get_mut(from).unwrap().remove();
                     board.get_mut(raycast.last().unwrap()).unwrap().replace(ram);
                 } else {
                     let ram = board.get_mut(from).unwrap().remove();
                     let mut to = collision.clone();
-                    board.attack_piece(from, collision);
+                    board.ability_attack_piece(from, collision);
                     for i in 0..charge {
                         let prev = to.clone();
                         to = to.direction_shift(&direction).unwrap();
-                        if board.get(&to).unwrap().piece.is_impenetrable(strength) {
+                        if stops(board, &to) {
                             board.get_mut(&prev).unwrap().replace(ram);
                             break;
                         }
-                        board.attack_piece(from, &to);
+                        board.ability_attack_piece(from, &to);
                         if i == charge - 1 {
                             board.get_mut(&to).unwrap().replace(ram);
                             break;
@@ -503,6 +558,7 @@ impl Ability for ShieldBearer {
         AbilityData {
             cooldown: Time::rounds(15),
             cost: Mana(0),
+            description: "Makes the nearby pieces impenetrable.",
         }
     }
 
@@ -528,6 +584,7 @@ impl Ability for Ship {
         AbilityData {
             cooldown: Time::rounds(12),
             cost: Mana(0),
+            description: "Attacks the tiles on its east and west sides.",
         }
     }
 
@@ -550,7 +607,8 @@ impl Ability for Ship {
         .flatten()
         {
             if let Some(attack_point) = from.subdirection_shift(&subdir) {
-                board.attack_piece(from, &attack_point);
+                // heroic, immune and invulnerable pieces survive the broadside
+                board.ability_attack_piece(from, &attack_point);
             }
         }
     }
@@ -563,6 +621,7 @@ impl Ability for SuperPawn {
         AbilityData {
             cooldown: Time::rounds(10),
             cost: Mana(0),
+            description: "Becomes immune and impenetrable.",
         }
     }
 
@@ -585,6 +644,8 @@ impl Ability for TeslaTower {
         AbilityData {
             cooldown: Time::rounds(10),
             cost: Mana(1),
+            description:
+                "After 2 turns, deactivates the enemy structures up to 3 tiles away for 6 rounds.",
         }
     }
 
@@ -618,6 +679,7 @@ impl Ability for Warlock {
         AbilityData {
             cooldown: Time::rounds(5),
             cost: Mana(3),
+            description: "Opens portals on the nearby empty magic tiles.",
         }
     }
 
@@ -644,15 +706,53 @@ impl Ability for Portal {
         AbilityData {
             cooldown: Time::turns(1),
             cost: Mana(0),
+            description:
+                "Sends an adjacent allied transportable piece next to another allied portal.",
         }
     }
 
-    fn can_use(_board: &Board, _from: &Pos, _info: &Info) -> bool {
-        true
+    fn can_use(board: &Board, from: &Pos, info: &Info) -> bool {
+        match info {
+            Info::Pos(to) => {
+                to != from
+                    && matches!(board.get_piece(to), Some(Piece::Portal(_)))
+                    && board.same_color(from, to)
+                    && Portal::traveler(board, from).is_some()
+                    && Portal::exit(board, to).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn r#use(board: &mut Board, from: &Pos, info: Info) {
+        if let Info::Pos(to) = info {
+            if let (Some(traveler), Some(exit)) =
+                (Portal::traveler(board, from), Portal::exit(board, &to))
+            {
+                let piece = board.get_mut(&traveler).unwrap().remove();
+                board.get_mut(&exit).unwrap().replace(piece);
+            }
+        } else {
+            panic!("Non pos info for portal")
+        }
     }
+}
 
-    fn r#use(_board: &mut Board, _from: &Pos, _info: Info) {
-        unimplemented!("portal::use not implemented yet")
+impl Portal {
+    /// The first allied transportable piece next to the portal, the one that goes through it.
+    fn traveler(board: &Board, portal: &Pos) -> Option<Pos> {
+        board
+            .iter_from_pattern(portal, pattern::king)
+            .find(|tile| tile.piece.weight().is_some() && board.same_color(portal, tile.pos()))
+            .map(|tile| tile.pos().clone())
+    }
+
+    /// The first empty tile next to the portal, where the traveler comes out.
+    fn exit(board: &Board, portal: &Pos) -> Option<Pos> {
+        board
+            .iter_from_pattern(portal, pattern::king)
+            .find(|tile| tile.is_empty())
+            .map(|tile| tile.pos().clone())
     }
 }
 
@@ -660,6 +760,60 @@ impl Ability for Portal {
 mod tests {
     use super::*;
 
+    #[test]
+    fn pawn_goes_through_portals() {
+        let mut board = Board::default();
+        let (portal, other, enemy, pawn) = (
+            Pos::new(1, 1),
+            Pos::new(6, 6),
+            Pos::new(4, 4),
+            Pos::new(1, 2),
+        );
+        for (pos, piece) in [
+            (&portal, Piece::portal(Color::White)),
+            (&other, Piece::portal(Color::White)),
+            (&enemy, Piece::portal(Color::Black)),
+            (&pawn, Piece::pawn(Color::White)),
+        ] {
+            board.get_mut(pos).unwrap().replace(piece);
+        }
+        let to = |pos: &Pos| Info::Pos(pos.clone());
+        assert!(!Portal::can_use(&board, &portal, &to(&enemy)));
+        assert!(!Portal::can_use(&board, &portal, &to(&portal)));
+        assert!(!Portal::can_use(&board, &other, &to(&portal)));
+        assert!(Portal::can_use(&board, &portal, &to(&other)));
+
+        Portal::r#use(&mut board, &portal, to(&other));
+        assert!(board.is_empty(&pawn));
+        let arrived = board
+            .iter_from_pattern(&other, pattern::king)
+            .filter(|tile| matches!(tile.piece, Piece::Pawn(_)))
+            .count();
+        assert_eq!(arrived, 1);
+        assert!(!Portal::can_use(&board, &portal, &to(&other)));
+    }
+
+    #[test]
+    fn ability_descriptions() {
+        let info = Piece::king(Color::White).ability_description().unwrap();
+        assert_eq!(info.name, "King");
+        assert_eq!(info.cost, Mana(2));
+        assert_eq!(info.cooldown, Time::default());
+        assert_eq!(
+            info.description,
+            "Teleports to any empty tile up to 5 tiles away."
+        );
+
+        assert_eq!(
+            Piece::knight(Color::Black)
+                .ability_description()
+                .map(|info| info.cooldown),
+            Some(Time::rounds(10))
+        );
+        assert_eq!(Piece::archer(Color::White).ability_description(), None);
+        assert_eq!(Piece::None.ability_description(), None);
+    }
+
     #[test]
     fn test_use() {
         // Create a new Board object
@@ -0,0 +1,203 @@
+//!
+//! A small two plies search to play carlettos chess against the computer.
+//! The evaluation is configured through `AiConfig`, so it can be tuned without touching the search.
+use std::collections::HashMap;
+
+use crate::{board::Board, chess_controller::CChess, piece::Piece, Action, Color};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiConfig {
+    /// Value of each piece, keyed by `Piece::name`. Missing pieces are worth nothing.
+    pub piece_weights: HashMap<&'static str, i32>,
+    /// Value of each action a player has more than its opponent.
+    pub mobility_weight: i32,
+    /// Value of each allied piece next to the king.
+    pub king_safety_weight: i32,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        Self {
+            piece_weights: HashMap::from([
+                ("Pawn", 100),
+                ("Knight", 300),
+                ("Bishop", 300),
+                ("Rook", 500),
+                ("Queen", 900),
+                ("King", 10000),
+                ("Archer", 300),
+                ("Ballista", 500),
+                ("Builder", 200),
+                ("Cannon", 600),
+                ("Catapult", 400),
+                ("CrazyPawn", 150),
+                ("Magician", 400),
+                ("Paladin", 500),
+                ("Ram", 300),
+                ("ShieldBearer", 300),
+                ("Ship", 400),
+                ("SuperPawn", 200),
+                ("TeslaTower", 500),
+                ("Wall", 100),
+                ("Warlock", 600),
+                ("Portal", 300),
+            ]),
+            mobility_weight: 5,
+            king_safety_weight: 20,
+        }
+    }
+}
+
+impl AiConfig {
+    pub fn piece_weight(&self, piece: &Piece) -> i32 {
+        self.piece_weights.get(piece.name()).copied().unwrap_or(0)
+    }
+
+    /// Score of the board for `color`, positive when `color` is ahead.
+    pub fn evaluate(&self, board: &Board, color: &Color) -> i32 {
+        let mut score = self.position_score(board, color);
+        if self.mobility_weight != 0 {
+            let enemy = color.opposite();
+            score += self.mobility_score(
+                board.actions_of(color).len(),
+                board.actions_of(&enemy).len(),
+            );
+        }
+        score
+    }
+
+    /// Material and king safety of `evaluate`, which don't need the actions of the players.
+    fn position_score(&self, board: &Board, color: &Color) -> i32 {
+        let enemy = color.opposite();
+        let material =
+            |color: &Color| -> i32 { board.pieces(color).map(|(_, p)| self.piece_weight(p)).sum() };
+        let mut score = material(color) - material(&enemy);
+        if self.king_safety_weight != 0 {
+            let safety = king_guards(board, color) - king_guards(board, &enemy);
+            score += self.king_safety_weight * safety;
+        }
+        score
+    }
+
+    fn mobility_score(&self, actions: usize, enemy_actions: usize) -> i32 {
+        self.mobility_weight * (actions as i32 - enemy_actions as i32)
+    }
+}
+
+fn king_guards(board: &Board, color: &Color) -> i32 {
+    board
+        .pieces(color)
+        .filter(|(_, piece)| matches!(piece, Piece::King(_)))
+        .flat_map(|(pos, _)| {
+            let mut around = board.neighbors(&pos);
+            around.extend(board.diagonal_neighbors(&pos));
+            around
+        })
+        .filter(|pos| board.get_piece(pos).and_then(Piece::color) == Some(color))
+        .count() as i32
+}
+
+/// The action of the current player whose best reply leaves it with the highest score.
+/// Ticks are not simulated, the players are assumed to alternate one action each.
+/// Listing the actions costs as much as the whole reply search, so the mobility is measured
+/// once after each action, rather than after every reply.
+pub fn best_action(board: &Board, config: &AiConfig) -> Option<Action> {
+    let color = board.current_player().color().clone();
+    let enemy = color.opposite();
+    board.actions_of(&color).into_iter().max_by_key(|action| {
+        let mut after = board.clone();
+        after.make(action.clone());
+        let replies = after.actions_of(&enemy);
+        let mobility = if config.mobility_weight != 0 {
+            config.mobility_score(after.actions_of(&color).len(), replies.len())
+        } else {
+            0
+        };
+        let score = replies
+            .into_iter()
+            .map(|reply| {
+                let mut after_reply = after.clone();
+                after_reply.make(reply);
+                config.position_score(&after_reply, &color)
+            })
+            .min()
+            .unwrap_or_else(|| config.position_score(&after, &color));
+        score + mobility
+    })
+}
+
+impl CChess {
+    pub fn ai_move(&self, config: &AiConfig) -> Option<Action> {
+        best_action(&self.board, config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{board::shape::Shape, Pos};
+
+    fn place(board: &mut Board, pos: (usize, usize), piece: Piece) {
+        board
+            .get_mut(&Pos::new(pos.0, pos.1))
+            .unwrap()
+            .replace(piece);
+    }
+
+    #[test]
+    fn ballista_weight_changes_the_trade() {
+        let mut board = Board::with_empty_tiles(Shape::default_chessboard());
+        // the black rook threatens the ballista, while the bishop can take the black knight
+        place(&mut board, (0, 0), Piece::ballista(Color::White));
+        place(&mut board, (2, 2), Piece::bishop(Color::White));
+        place(&mut board, (0, 7), Piece::rook(Color::Black));
+        place(&mut board, (4, 4), Piece::knight(Color::Black));
+        let chess = CChess {
+            board,
+            ..Default::default()
+        };
+
+        let mut config = AiConfig {
+            mobility_weight: 0,
+            king_safety_weight: 0,
+            ..Default::default()
+        };
+        config.piece_weights.insert("Ballista", 100);
+        let action = chess.ai_move(&config).unwrap();
+        assert!(
+            matches!(&action, Action::Take { from, to } if from == &Pos::new(2, 2) && to == &Pos::new(4, 4)),
+            "{action:?}"
+        );
+
+        config.piece_weights.insert("Ballista", 1000);
+        let action = chess.ai_move(&config).unwrap();
+        assert!(
+            matches!(&action, Action::Move { from, to } if from == &Pos::new(0, 0) && to == &Pos::new(1, 0)),
+            "{action:?}"
+        );
+    }
+
+    #[test]
+    fn default_config_takes_the_queen() {
+        let mut board = Board::default_chessboard();
+        let queen = board
+            .pieces(&Color::Black)
+            .find(|(_, piece)| matches!(piece, Piece::Queen(_)))
+            .map(|(pos, _)| pos)
+            .unwrap();
+        let knight = Pos::new(queen.x + 1, queen.y - 2);
+        board
+            .get_mut(&knight)
+            .unwrap()
+            .replace(Piece::knight(Color::White));
+        let chess = CChess {
+            board,
+            ..Default::default()
+        };
+        let action = chess.ai_move(&AiConfig::default()).unwrap();
+        assert!(
+            matches!(&action, Action::Take { to, .. } if to == &queen),
+            "{action:?}"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use std::{
-    mem,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    iter, mem,
     ops::{Add, AddAssign, Sub, SubAssign},
 };
 
@@ -10,7 +12,7 @@ use crate::{
     card::{Card, CardPlace, Cards},
     pattern,
     piece::{Effect, Piece, PieceData, Type},
-    Action, Color, Pos, Time,
+    Action, Color, Info, Pos, Time,
 };
 
 use self::shape::Shape;
@@ -18,6 +20,9 @@ use self::shape::Shape;
 #[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, Clone)]
 pub struct Mana(pub usize);
 
+/// The mana given to the owner of a demonic piece when it dies.
+pub const DEMONIC_MANA: Mana = Mana(1);
+
 impl Add for Mana {
     type Output = Mana;
     fn add(self, rhs: Self) -> Self::Output {
@@ -41,14 +46,14 @@ impl AddAssign for Mana {
 impl Sub for Mana {
     type Output = Mana;
     fn sub(self, rhs: Self) -> Self::Output {
-        Mana(self.0.checked_sub(rhs.0).unwrap_or_default())
+        Mana(self.0.saturating_sub(rhs.0))
     }
 }
 
 impl Sub for &Mana {
     type Output = Mana;
     fn sub(self, rhs: Self) -> Self::Output {
-        Mana(self.0.checked_sub(rhs.0).unwrap_or_default())
+        Mana(self.0.saturating_sub(rhs.0))
     }
 }
 
@@ -61,6 +66,26 @@ impl SubAssign for Mana {
 #[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, Clone)]
 pub struct Movements(pub usize);
 
+/// How many actions of each kind were made in the game, counted by `Board::make`.
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq, Clone)]
+pub struct GameStats {
+    pub moves: usize,
+    pub takes: usize,
+    pub attacks: usize,
+    pub abilities: usize,
+}
+
+impl GameStats {
+    fn count(&mut self, action: &Action) {
+        match action {
+            Action::Move { .. } => self.moves += 1,
+            Action::Take { .. } => self.takes += 1,
+            Action::Attack { .. } => self.attacks += 1,
+            Action::Ability { .. } => self.abilities += 1,
+        }
+    }
+}
+
 impl Add for Movements {
     type Output = Movements;
     fn add(self, rhs: Self) -> Self::Output {
@@ -84,14 +109,14 @@ impl AddAssign for Movements {
 impl Sub for Movements {
     type Output = Movements;
     fn sub(self, rhs: Self) -> Self::Output {
-        Movements(self.0.checked_sub(rhs.0).unwrap_or_default())
+        Movements(self.0.saturating_sub(rhs.0))
     }
 }
 
 impl Sub for &Movements {
     type Output = Movements;
     fn sub(self, rhs: Self) -> Self::Output {
-        Movements(self.0.checked_sub(rhs.0).unwrap_or_default())
+        Movements(self.0.saturating_sub(rhs.0))
     }
 }
 
@@ -124,6 +149,12 @@ impl Player {
         }
     }
 
+    /// The same player, making `movements` movements each turn instead of one.
+    pub fn with_movements(mut self, movements: Movements) -> Self {
+        self.movements = movements;
+        self
+    }
+
     pub fn color(&self) -> &Color {
         &self.color
     }
@@ -157,6 +188,8 @@ pub struct Tile {
     pub magic: bool,
     pub buildable: bool,
     pub piece: Piece,
+    // Not serialized, the board restores it from the tile index in its shape.
+    #[serde(skip)]
     pos: Pos,
 }
 
@@ -285,6 +318,14 @@ pub mod shape {
                 && pos.y < self.north()
         }
 
+        /// Index of `pos` within `points_iter`, if the square contains it.
+        pub fn index_of(&self, pos: &Pos) -> Option<usize> {
+            if !self.contains(pos) {
+                return None;
+            }
+            Some((pos.x - self.west()) * self.height + (pos.y - self.south()))
+        }
+
         pub fn points_iter(&self) -> impl Iterator<Item = Pos> + '_ {
             (self.west()..self.west() + self.width).flat_map(|x| {
                 (self.south()..self.south() + self.height).map(move |y| Pos::new(x, y))
@@ -354,10 +395,32 @@ pub mod shape {
             self.squares.iter().any(|square| square.contains(pos))
         }
 
+        /// Index of `pos` within `points_iter`, computed from the squares without iterating the points.
+        pub fn index_of(&self, pos: &Pos) -> Option<usize> {
+            let mut offset = 0;
+            for square in self.squares.iter() {
+                if let Some(i) = square.index_of(pos) {
+                    return Some(offset + i);
+                }
+                offset += square.width * square.height;
+            }
+            None
+        }
+
+        /// Every point, square by square and column by column within each square (x outer, y inner).
+        /// This is the order of the tiles of a `Board`.
         pub fn points_iter(&self) -> impl Iterator<Item = Pos> + '_ {
             self.squares.iter().flat_map(|s| s.points_iter())
         }
 
+        /// Every point, row by row from south to north and west to east within each row
+        /// (y outer, x inner), the order renderers draw them.
+        pub fn points_row_major(&self) -> impl Iterator<Item = Pos> + '_ {
+            (0..self.height())
+                .flat_map(move |y| (0..self.width()).map(move |x| Pos::new(x, y)))
+                .filter(|pos| self.contains(pos))
+        }
+
         pub fn height(&self) -> usize {
             self.squares
                 .iter()
@@ -365,153 +428,125 @@ pub mod shape {
                 .max()
                 .unwrap_or_default()
         }
+
+        pub fn width(&self) -> usize {
+            self.squares
+                .iter()
+                .map(|s| s.east())
+                .max()
+                .unwrap_or_default()
+        }
     }
 }
 
+/// A position and the constructor of the piece placed there, see `Board::mirror_setup`.
+pub type Placement = (Pos, fn(Color) -> Piece);
+
+/// The tiles are kept in the order of `shape.points_iter()`, so the position of each tile
+/// can be derived from its index and is not serialized.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(from = "BoardData")]
 pub struct Board {
     pub tiles: Vec<Tile>,
     pub rng: BoardRng,
     pub time: Time,
+    /// The game ends in a draw once this many rounds have passed.
+    pub max_rounds: Option<usize>,
     players: Vec<Player>,
     cards: Cards,
     dead_pieces: Vec<Piece>,
     shape: Shape,
     events: Events,
+    stats: GameStats,
 }
 
 impl Board {
     pub fn default_chessboard() -> Self {
         let mut default = Self::default();
+        default.mirror_setup(&[
+            (Pos::new(0, 0), Piece::rook),
+            (Pos::new(1, 0), Piece::knight),
+            (Pos::new(2, 0), Piece::bishop),
+            (Pos::new(3, 0), Piece::queen),
+            (Pos::new(4, 0), Piece::king),
+            (Pos::new(5, 0), Piece::bishop),
+            (Pos::new(6, 0), Piece::knight),
+            (Pos::new(7, 0), Piece::rook),
+            (Pos::new(0, 1), Piece::pawn),
+            (Pos::new(1, 1), Piece::pawn),
+            (Pos::new(2, 1), Piece::pawn),
+            (Pos::new(3, 1), Piece::pawn),
+            (Pos::new(4, 1), Piece::pawn),
+            (Pos::new(5, 1), Piece::pawn),
+            (Pos::new(6, 1), Piece::pawn),
+            (Pos::new(7, 1), Piece::pawn),
+        ]);
         default
-            .get_mut(&Pos::new(0, 0))
-            .unwrap()
-            .replace(Piece::rook(Color::White));
-        default
-            .get_mut(&Pos::new(1, 0))
-            .unwrap()
-            .replace(Piece::knight(Color::White));
-        default
-            .get_mut(&Pos::new(2, 0))
-            .unwrap()
-            .replace(Piece::bishop(Color::White));
-        default
-            .get_mut(&Pos::new(3, 0))
-            .unwrap()
-            .replace(Piece::queen(Color::White));
-        default
-            .get_mut(&Pos::new(4, 0))
-            .unwrap()
-            .replace(Piece::king(Color::White));
-        default
-            .get_mut(&Pos::new(5, 0))
-            .unwrap()
-            .replace(Piece::bishop(Color::White));
-        default
-            .get_mut(&Pos::new(6, 0))
-            .unwrap()
-            .replace(Piece::knight(Color::White));
-        default
-            .get_mut(&Pos::new(7, 0))
-            .unwrap()
-            .replace(Piece::rook(Color::White));
-        default
-            .get_mut(&Pos::new(0, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(1, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(2, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(3, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(4, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(5, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(6, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(7, 1))
-            .unwrap()
-            .replace(Piece::pawn(Color::White));
-        default
-            .get_mut(&Pos::new(0, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(1, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(2, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(3, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(4, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(5, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(6, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(7, 6))
-            .unwrap()
-            .replace(Piece::pawn(Color::Black));
-        default
-            .get_mut(&Pos::new(0, 7))
-            .unwrap()
-            .replace(Piece::rook(Color::Black));
-        default
-            .get_mut(&Pos::new(1, 7))
-            .unwrap()
-            .replace(Piece::knight(Color::Black));
-        default
-            .get_mut(&Pos::new(2, 7))
-            .unwrap()
-            .replace(Piece::bishop(Color::Black));
-        default
-            .get_mut(&Pos::new(3, 7))
-            .unwrap()
-            .replace(Piece::queen(Color::Black));
-        default
-            .get_mut(&Pos::new(4, 7))
-            .unwrap()
-            .replace(Piece::king(Color::Black));
-        default
-            .get_mut(&Pos::new(5, 7))
-            .unwrap()
-            .replace(Piece::bishop(Color::Black));
-        default
-            .get_mut(&Pos::new(6, 7))
-            .unwrap()
-            .replace(Piece::knight(Color::Black));
-        default
-            .get_mut(&Pos::new(7, 7))
-            .unwrap()
-            .replace(Piece::rook(Color::Black));
-        default
+    }
+
+    /// Places the white pieces in the given positions, and the black ones in the same positions
+    /// mirrored vertically. Positions outside the board are skipped.
+    pub fn mirror_setup(&mut self, placements: &[Placement]) {
+        let height = self.height();
+        for (pos, piece) in placements {
+            if let Some(tile) = self.get_mut(pos) {
+                tile.replace(piece(Color::White));
+            }
+            let Some(y) = height.checked_sub(pos.y + 1) else {
+                continue;
+            };
+            if let Some(tile) = self.get_mut(&Pos::new(pos.x, y)) {
+                tile.replace(piece(Color::Black));
+            }
+        }
+    }
+
+    /// Makes the tiles at `positions` magic, and every other tile not magic.
+    /// Positions outside the board are skipped.
+    pub fn set_magic(&mut self, positions: &[Pos]) {
+        for tile in self.tiles.iter_mut() {
+            tile.magic = positions.contains(tile.pos());
+        }
+    }
+
+    /// Sets whether the Builder and the Warlock can build on the tile at `pos`, return false
+    /// if the position is not on the board.
+    pub fn set_buildable(&mut self, pos: &Pos, buildable: bool) -> bool {
+        match self.get_mut(pos) {
+            Some(tile) => {
+                tile.buildable = buildable;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The westmost and eastmost tiles of the two rows around the middle of the board,
+    /// where the Warlock opens its portals. They are the same for both players.
+    pub fn symmetric_magic(&self) -> Vec<Pos> {
+        let height = self.height();
+        let rows = match height {
+            0 => return Vec::new(),
+            h if h % 2 == 1 && h > 1 => [h / 2 - 1, h / 2 + 1],
+            h => [h.saturating_sub(1) / 2, h / 2],
+        };
+        let mut positions = Vec::new();
+        for row in rows {
+            let xs = self
+                .row_iter(row)
+                .map(|tile| tile.pos().x)
+                .collect::<Vec<_>>();
+            let (Some(&west), Some(&east)) = (xs.iter().min(), xs.iter().max()) else {
+                continue;
+            };
+            for x in [west, east] {
+                let pos = Pos::new(x, row);
+                if !positions.contains(&pos) {
+                    positions.push(pos);
+                }
+            }
+        }
+        positions
     }
 
     pub fn cchessboard() -> Self {
@@ -538,10 +573,7 @@ impl Board {
             shape,
             ..Default::default()
         };
-        board.get_mut(&Pos::new(0, 7)).unwrap().magic = true;
-        board.get_mut(&Pos::new(0, 9)).unwrap().magic = true;
-        board.get_mut(&Pos::new(15, 7)).unwrap().magic = true;
-        board.get_mut(&Pos::new(15, 9)).unwrap().magic = true;
+        board.set_magic(&board.symmetric_magic());
 
         board
             .get_mut(&Pos::new(0, 0))
@@ -946,7 +978,8 @@ impl Board {
         }
     }
 
-    pub fn with_default_players(tiles: Vec<Tile>, shape: Shape) -> Self {
+    pub fn with_default_players(mut tiles: Vec<Tile>, shape: Shape) -> Self {
+        tiles.sort_by_key(|tile| shape.index_of(tile.pos()));
         Self {
             tiles,
             shape,
@@ -962,7 +995,8 @@ impl Board {
         }
     }
 
-    pub fn new(tiles: Vec<Tile>, shape: Shape, players: Vec<Player>) -> Self {
+    pub fn new(mut tiles: Vec<Tile>, shape: Shape, players: Vec<Player>) -> Self {
+        tiles.sort_by_key(|tile| shape.index_of(tile.pos()));
         Self {
             tiles,
             shape,
@@ -990,19 +1024,53 @@ impl Board {
         let last = self
             .dead_pieces
             .iter()
-            .rev()
-            .position(|x| x.color() == Some(color));
+            .rposition(|x| x.color() == Some(color));
         match last {
             None => Piece::None,
             Some(i) => self.dead_pieces.remove(i),
         }
     }
 
-    pub fn get_nearby_tiles(&self, pos: &Pos) -> Vec<&Tile> {
-        vec![pos.north(), pos.east(), pos.south(), pos.west()]
+    /// Brings back the last dead piece of `color` on the empty tile `to`, without the
+    /// effects it had when it died. Callers that revive it under other rules, like a
+    /// different owner, can change the piece at `to` afterwards.
+    pub fn resurrect(&mut self, color: &Color, to: &Pos) -> Result<(), BoardError> {
+        match self.get(to) {
+            None => return Err(BoardError::OutOfBoard),
+            Some(tile) if tile.has_piece() => return Err(BoardError::OccupiedTile),
+            Some(_) => (),
+        }
+        if self.get_last_dead_with_color(color).is_none() {
+            return Err(BoardError::NoDeadPiece);
+        }
+        let piece = self.remove_last_dead_with_color(color);
+        self.get_mut(to).unwrap().replace(piece);
+        self.clear_effects(to);
+        Ok(())
+    }
+
+    /// The orthogonal neighbors of `pos` that are inside the board.
+    pub fn neighbors(&self, pos: &Pos) -> Vec<Pos> {
+        [pos.north(), pos.east(), pos.south(), pos.west()]
             .into_iter()
             .flatten()
-            .filter_map(|p| self.get(&p))
+            .filter(|p| self.contains(p))
+            .collect()
+    }
+
+    /// The diagonal neighbors of `pos` that are inside the board.
+    pub fn diagonal_neighbors(&self, pos: &Pos) -> Vec<Pos> {
+        [(1, 1), (1, -1), (-1, -1), (-1, 1)]
+            .into_iter()
+            .filter_map(|(x, y)| pos.shift(x, y))
+            .filter(|p| self.contains(p))
+            .collect()
+    }
+
+    pub fn get_nearby_tiles(&self, pos: &Pos) -> Vec<&Tile> {
+        self.neighbors(pos)
+            .iter()
+            .filter_map(|p| self.get(p))
             .collect()
     }
 
@@ -1010,12 +1078,26 @@ impl Board {
         self.shape.contains(pos)
     }
 
+    /// Index of the tile at `pos`.
+    /// Tiles built from the shape are looked up directly, otherwise it falls back to a linear search.
+    fn tile_index(&self, pos: &Pos) -> Option<usize> {
+        match self.shape.index_of(pos) {
+            Some(i) if self.tiles.get(i).is_some_and(|t| &t.pos == pos) => Some(i),
+            _ => self.tiles.iter().position(|t| &t.pos == pos),
+        }
+    }
+
     pub fn get(&self, pos: &Pos) -> Option<&Tile> {
-        self.tiles.iter().find(|t| &t.pos == pos)
+        self.tile_index(pos).map(|i| &self.tiles[i])
     }
 
     pub fn get_mut(&mut self, pos: &Pos) -> Option<&mut Tile> {
-        self.tiles.iter_mut().find(|t| &t.pos == pos)
+        self.tile_index(pos).map(|i| &mut self.tiles[i])
+    }
+
+    /// Returns the tiles at the given positions, in the same order, with `None` for positions outside the board.
+    pub fn get_many(&self, positions: &[Pos]) -> Vec<Option<&Tile>> {
+        positions.iter().map(|pos| self.get(pos)).collect()
     }
 
     pub fn is_empty(&self, pos: &Pos) -> bool {
@@ -1097,39 +1179,232 @@ impl Board {
         self.ray_cast(from, len, shift, |t| t.has_piece())
     }
 
+    /// Iterates the tiles in the direction of `shift`, starting next to `from`.
+    /// The iterator ends when leaving the board, or after yielding the first tile with a piece.
+    pub fn ray_iter(&self, from: &Pos, shift: (isize, isize)) -> impl Iterator<Item = &Tile> + '_ {
+        let mut blocked = shift == (0, 0) || !self.contains(from);
+        iter::successors(from.shift(shift.0, shift.1), move |pos| {
+            pos.shift(shift.0, shift.1)
+        })
+        .map_while(|pos| self.get(&pos))
+        .take_while(move |tile| {
+            let take = !blocked;
+            blocked = tile.has_piece();
+            take
+        })
+    }
+
     ///
     /// Returns the tiles in the same row as the given position.
     pub fn row_iter(&self, row: usize) -> impl Iterator<Item = &Tile> {
         self.tiles.iter().filter(move |t| t.pos.y == row)
     }
 
+    ///
+    /// Returns the tiles in the given column, from south to north.
+    pub fn column_iter(&self, column: usize) -> impl Iterator<Item = &Tile> {
+        self.tiles.iter().filter(move |t| t.pos.x == column)
+    }
+
     pub fn height(&self) -> usize {
         self.shape.height()
     }
 
+    pub fn width(&self) -> usize {
+        self.shape.width()
+    }
+
+    ///
+    /// Returns the rows from the top of the screen to the bottom.
+    /// The row 0 is drawn at the bottom, unless the board is `flipped`.
+    pub fn display_rows(&self, flipped: bool) -> Vec<usize> {
+        if flipped {
+            (0..self.height()).collect()
+        } else {
+            (0..self.height()).rev().collect()
+        }
+    }
+
+    ///
+    /// Returns the tiles of the row from the left of the screen to the right, reversed if `flipped`.
+    pub fn display_row(&self, row: usize, flipped: bool) -> Vec<&Tile> {
+        let mut tiles: Vec<&Tile> = self.row_iter(row).collect();
+        if flipped {
+            tiles.reverse();
+        }
+        tiles
+    }
+
     pub fn move_piece(&mut self, from: &Pos, to: &Pos) {
         let piece = self.get_mut(from).unwrap().remove();
         self.get_mut(to).unwrap().replace(piece);
     }
 
-    pub fn take_piece(&mut self, from: &Pos, to: &Pos) {
+    /// Whether there is a piece at `to` that can be taken, invulnerable pieces can't.
+    pub fn can_take_piece(&self, from: &Pos, to: &Pos) -> bool {
+        self.get_data(to)
+            .is_some_and(|data| data.can_be(&Action::take(from, to)))
+    }
+
+    /// Moves the piece at `from` onto `to`, killing the piece there. Return false and leave
+    /// both pieces in place if it can't be taken, see `Board::can_take_piece`.
+    pub fn take_piece(&mut self, from: &Pos, to: &Pos) -> bool {
+        if !self.can_take_piece(from, to) {
+            return false;
+        }
         let piece = self.get_mut(from).unwrap().remove();
         let dead = self.get_mut(to).unwrap().replace(piece);
-        self.dead_pieces.push(dead);
+        self.bury(dead, &Action::take(from, to));
+        true
+    }
+
+    /// Whether there is a piece at `to` that can be attacked, heroic and invulnerable
+    /// pieces can't.
+    pub fn can_attack_piece(&self, from: &Pos, to: &Pos) -> bool {
+        self.get_data(to)
+            .is_some_and(|data| data.can_be(&Action::attack(from, to)))
+    }
+
+    /// Kills the piece at `to`, return false and leave it there if it can't be attacked,
+    /// see `Board::can_attack_piece`.
+    pub fn attack_piece(&mut self, from: &Pos, to: &Pos) -> bool {
+        self.try_remove(to, &Action::attack(from, to))
+    }
+
+    /// Whether the ability of the piece at `from` can kill the piece at `to`, which also
+    /// spares the immune pieces.
+    pub fn can_ability_attack_piece(&self, from: &Pos, to: &Pos) -> bool {
+        let ability = Action::ability(from, Info::Pos(to.clone()));
+        self.can_attack_piece(from, to)
+            && self.get_data(to).is_some_and(|data| data.can_be(&ability))
+    }
+
+    /// The attacks made by abilities, see `Board::can_ability_attack_piece`.
+    pub fn ability_attack_piece(&mut self, from: &Pos, to: &Pos) -> bool {
+        self.can_ability_attack_piece(from, to) && self.attack_piece(from, to)
     }
 
-    pub fn attack_piece(&mut self, _from: &Pos, to: &Pos) {
-        let dead = self.get_mut(to).unwrap().remove();
+    /// Kills the piece at `pos` if it can be the target of `action`, return false and
+    /// leave it there otherwise.
+    pub fn try_remove(&mut self, pos: &Pos, action: &Action) -> bool {
+        if !self.get_data(pos).is_some_and(|data| data.can_be(action)) {
+            return false;
+        }
+        let dead = self.get_mut(pos).unwrap().remove();
+        self.bury(dead, action);
+        true
+    }
+
+    /// Lets the killed piece react to the `action` that killed it, like the demonic ones
+    /// giving mana to their owner, and keeps it with the dead ones.
+    fn bury(&mut self, dead: Piece, action: &Action) {
+        if let Some(data) = dead.data() {
+            data.on_be(self, action);
+        }
         self.dead_pieces.push(dead);
     }
 
-    pub fn make(&mut self, action: Action) {
-        match action {
-            Action::Move { from, to } => self.move_piece(&from, &to),
-            Action::Take { from, to } => self.take_piece(&from, &to),
-            Action::Attack { from, to } => self.attack_piece(&from, &to),
-            Action::Ability { from, info } => Piece::ability(self, from, info),
+    /// Whether the piece at `carrier` can carry the adjacent piece at `cargo`.
+    pub fn can_transport(&self, carrier: &Pos, cargo: &Pos) -> bool {
+        let (Some(carrier_piece), Some(cargo_piece)) =
+            (self.get_piece(carrier), self.get_piece(cargo))
+        else {
+            return false;
+        };
+        carrier != cargo
+            && pattern::square(carrier, cargo, 1)
+            && carrier_piece
+                .carry_capacity()
+                .is_some_and(|capacity| cargo_piece.is_transportable(&capacity))
+    }
+
+    /// Moves the carrier to `to`, and the cargo along with it, keeping them side by side.
+    /// Returns false, leaving the board untouched, if the cargo can't be carried or the
+    /// tiles they'd land on aren't free.
+    pub fn transport(&mut self, carrier: &Pos, cargo: &Pos, to: &Pos) -> bool {
+        if !self.can_transport(carrier, cargo) {
+            return false;
+        }
+        let shift = (
+            to.x as isize - carrier.x as isize,
+            to.y as isize - carrier.y as isize,
+        );
+        let Some(cargo_to) = cargo.shift(shift.0, shift.1) else {
+            return false;
+        };
+        let is_free = |pos: &Pos| self.is_empty(pos) || pos == carrier || pos == cargo;
+        if !is_free(to) || !is_free(&cargo_to) {
+            return false;
+        }
+        let carrier_piece = self.get_mut(carrier).unwrap().remove();
+        let cargo_piece = self.get_mut(cargo).unwrap().remove();
+        self.get_mut(to).unwrap().replace(carrier_piece);
+        self.get_mut(&cargo_to).unwrap().replace(cargo_piece);
+        true
+    }
+
+    /// Whether the piece at `from` has an ability that is off cooldown and the current player
+    /// has the mana to pay for it.
+    pub fn can_afford_ability(&self, from: &Pos) -> bool {
+        let (Some(data), Some(ability)) = (
+            self.get_data(from),
+            self.get_piece(from).and_then(Piece::ability_description),
+        ) else {
+            return false;
+        };
+        data.cooldown.is_zero() && self.current_player().mana.0 >= ability.cost.0
+    }
+
+    /// Takes the cost of the ability of the piece at `from` from the current player and puts
+    /// the piece on cooldown, return false if it can't be afforded.
+    fn pay_ability(&mut self, from: &Pos) -> bool {
+        if !self.can_afford_ability(from) {
+            return false;
+        }
+        let ability = self
+            .get_piece(from)
+            .and_then(Piece::ability_description)
+            .unwrap();
+        self.mut_current_player().mana -= ability.cost;
+        self.get_mut_data(from).unwrap().cooldown = ability.cooldown;
+        true
+    }
+
+    /// Makes the action and calls `on_do` on the piece that made it.
+    /// Return false if the target refused to be taken or attacked, or the ability can't be
+    /// used or afforded, see `Board::can_afford_ability`. Nothing changes then.
+    pub fn make(&mut self, action: Action) -> bool {
+        let (made, actor) = match &action {
+            Action::Move { from, to } => {
+                self.move_piece(from, to);
+                (true, Some(to.clone()))
+            }
+            Action::Take { from, to } => (self.take_piece(from, to), Some(to.clone())),
+            Action::Attack { from, to } => (self.attack_piece(from, to), Some(from.clone())),
+            Action::Ability { from, info } => {
+                let usable = self
+                    .get_piece(from)
+                    .is_some_and(|piece| piece.can_do(self, action.clone()));
+                let made = usable && self.pay_ability(from);
+                if made {
+                    // before the ability, which may move the piece away from its tile
+                    self.get_mut_data(from).unwrap().on_do(&action);
+                    Piece::ability(self, from.clone(), info.clone());
+                }
+                (made, None)
+            }
+        };
+        if made {
+            self.stats.count(&action);
+            if let Some(data) = actor.and_then(|actor| self.get_mut_data(&actor)) {
+                data.on_do(&action);
+            }
         }
+        made
+    }
+
+    pub fn stats(&self) -> &GameStats {
+        &self.stats
     }
 
     ///
@@ -1145,6 +1420,7 @@ impl Board {
     /// ---> Effects::pre_tick
     /// ---> Cooldown
     /// ---> Effects::post_tick
+    /// -> Burnt pieces die
     /// Current Player (if round tick, then all the players)
     /// -> Mana (if round tick)
     /// -> DiscardPile
@@ -1154,25 +1430,30 @@ impl Board {
     /// Events
     /// RNG
     ///
+    /// Once the game is a draw, ticking does nothing.
     pub fn tick(&mut self) {
+        if self.is_draw() {
+            return;
+        }
         let movement = Time::movements(1);
         let turn = Time::turns(1);
         let round = Time::rounds(1);
 
         log::info!("movement tick");
         self.time.movement += 1;
-        self.iter_mut().for_each(|tile| tile.tick(&movement));
+        self.tick_tiles(&movement);
         self.mut_current_player().tick(&movement);
         self.cards.tick(&movement, CardPlace::OnBoard);
         self.events.tick(&movement);
         self.rng.next_movement();
 
-        if self.time.movement == self.current_player().movements.0 {
+        // a player always gets at least one movement
+        if self.time.movement >= self.current_player().movements.0.max(1) {
             log::info!("turn tick");
             self.time.movement = 0;
             let current_player_i = self.current_player().id;
             self.time.turn += 1;
-            self.iter_mut().for_each(|tile| tile.tick(&turn));
+            self.tick_tiles(&turn);
             self.mut_player_from_id(current_player_i)
                 .unwrap()
                 .tick(&turn);
@@ -1184,7 +1465,7 @@ impl Board {
                 log::info!("round tick");
                 self.time.turn = 0;
                 self.time.round += 1;
-                self.iter_mut().for_each(|tile| tile.tick(&round));
+                self.tick_tiles(&round);
                 self.players
                     .iter_mut()
                     .for_each(|player| player.tick(&round));
@@ -1195,6 +1476,57 @@ impl Board {
         }
     }
 
+    /// Ticks every tile, the pieces whose fire went out with this tick burn to death. The fire
+    /// attacks them from their own tile, so heroic and invulnerable pieces survive it.
+    fn tick_tiles(&mut self, time: &Time) {
+        let fire = mem::discriminant(&Effect::fire());
+        let is_burning = |tile: &Tile| tile.piece.data().is_some_and(|d| d.has_effect_kind(fire));
+        let burning = self
+            .tiles
+            .iter()
+            .filter(|tile| is_burning(tile))
+            .map(|tile| tile.pos().clone())
+            .collect::<Vec<_>>();
+        self.iter_mut().for_each(|tile| tile.tick(time));
+        for pos in burning {
+            if !is_burning(self.get(&pos).unwrap()) {
+                self.try_remove(&pos, &Action::attack(&pos, &pos));
+            }
+        }
+    }
+
+    /// Passes the current movement without an action, for players that can't or won't act.
+    /// Return false once the game is over.
+    pub fn skip_movement(&mut self) -> bool {
+        if self.is_draw() || self.winner().is_some() {
+            return false;
+        }
+        self.tick();
+        true
+    }
+
+    /// The color of the only player left with a king, once the kings of every other player
+    /// were killed. Boards that never had those kings have no winner.
+    pub fn winner(&self) -> Option<Color> {
+        let is_king = |piece: &Piece, color: &Color| matches!(piece, Piece::King(data) if &data.color == color);
+        let has_lost = |color: &Color| {
+            !self.tiles.iter().any(|tile| is_king(&tile.piece, color))
+                && self.dead_pieces.iter().any(|piece| is_king(piece, color))
+        };
+        let mut standing = self
+            .players
+            .iter()
+            .map(Player::color)
+            .filter(|color| !has_lost(color));
+        let winner = standing.next()?;
+        (self.players.len() > 1 && standing.next().is_none()).then(|| winner.clone())
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.max_rounds
+            .is_some_and(|max_rounds| self.time.round >= max_rounds)
+    }
+
     pub fn player_from_id(&self, player_id: usize) -> Option<&Player> {
         self.players.iter().find(|player| player.id == player_id)
     }
@@ -1215,6 +1547,13 @@ impl Board {
             .find(|player| &player.color == color)
     }
 
+    /// Gives every player `movements` movements per turn, from the next movement on.
+    pub fn set_movements_per_turn(&mut self, movements: Movements) {
+        self.players
+            .iter_mut()
+            .for_each(|player| player.movements = movements.clone());
+    }
+
     pub fn current_player(&self) -> &Player {
         &self.players[self.time.turn]
     }
@@ -1239,15 +1578,86 @@ impl Board {
         self.tiles.iter()
     }
 
-    pub fn iter_from_pattern<'a, F: Fn(&Pos, &Pos) -> bool + 'a>(
-        &'a self,
-        from: &'a Pos,
-        f: F,
-    ) -> impl Iterator<Item = &Tile> + '_ {
-        self.iter()
-            .filter(move |tile| tile.pos() != from && f(from, tile.pos()))
-    }
-
+    /// The moves, takes and attacks the piece at `from` can make, abilities are not included.
+    pub fn actions_from(&self, from: &Pos) -> Vec<Action> {
+        let Some(piece) = self.get_piece(from) else {
+            return Vec::new();
+        };
+        let mut actions = Vec::new();
+        for to in self.shape.points_iter() {
+            if &to == from {
+                continue;
+            }
+            let move_action = Action::r#move(from, &to);
+            let take_action = Action::take(from, &to);
+            let attack_action = Action::attack(from, &to);
+            if piece.can_do(self, move_action.clone()) && self.is_empty(&to) {
+                actions.push(move_action);
+            }
+            if self.has_piece(&to) && !self.same_color(from, &to) {
+                if piece.can_do(self, take_action.clone()) && self.can_take_piece(from, &to) {
+                    actions.push(take_action);
+                }
+                if piece.can_do(self, attack_action.clone()) && self.can_attack_piece(from, &to) {
+                    actions.push(attack_action);
+                }
+            }
+        }
+        actions
+    }
+
+    /// Every action the pieces of `color` can make.
+    pub fn actions_of(&self, color: &Color) -> Vec<Action> {
+        self.pieces(color)
+            .flat_map(|(pos, _)| self.actions_from(&pos))
+            .collect()
+    }
+
+    /// Compares everything the players can see, ignoring the rng and the pending events.
+    pub fn same_position(&self, other: &Board) -> bool {
+        self.tiles == other.tiles
+            && self.time == other.time
+            && self.players == other.players
+            && self.cards == other.cards
+            && self.dead_pieces == other.dead_pieces
+            && self.shape == other.shape
+    }
+
+    /// Hash of the pieces, the player in turn, its movement and the cards on the board, to detect
+    /// repeated positions. The rounds, the rng, the players and the pending events are ignored.
+    pub fn position_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.iter().for_each(|tile| tile.piece.hash(&mut hasher));
+        self.time.turn.hash(&mut hasher);
+        self.time.movement.hash(&mut hasher);
+        self.cards.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Every piece of the given color with its position.
+    pub fn pieces<'a>(&'a self, color: &'a Color) -> impl Iterator<Item = (Pos, &'a Piece)> + 'a {
+        self.iter()
+            .filter(move |tile| tile.piece.color() == Some(color))
+            .map(|tile| (tile.pos().clone(), &tile.piece))
+    }
+
+    pub fn empty_tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.iter().filter(|tile| tile.is_empty())
+    }
+
+    pub fn occupied_tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.iter().filter(|tile| tile.has_piece())
+    }
+
+    pub fn iter_from_pattern<'a, F: Fn(&Pos, &Pos) -> bool + 'a>(
+        &'a self,
+        from: &'a Pos,
+        f: F,
+    ) -> impl Iterator<Item = &'a Tile> + 'a {
+        self.iter()
+            .filter(move |tile| tile.pos() != from && f(from, tile.pos()))
+    }
+
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Tile> {
         self.tiles.iter_mut()
     }
@@ -1256,7 +1666,7 @@ impl Board {
         &'a mut self,
         from: &'a Pos,
         f: F,
-    ) -> impl Iterator<Item = &mut Tile> + '_ {
+    ) -> impl Iterator<Item = &'a mut Tile> + 'a {
         self.iter_mut()
             .filter(move |tile| tile.pos() != from && f(from, tile.pos()))
     }
@@ -1283,6 +1693,36 @@ impl Board {
         self.get_mut(pos).and_then(|tile| tile.piece.mut_data())
     }
 
+    /// Removes every effect of the piece at `pos`, if any.
+    pub fn clear_effects(&mut self, pos: &Pos) {
+        if let Some(data) = self.get_mut_data(pos) {
+            data.effects.0.clear();
+        }
+    }
+
+    /// What a spectator can see of the board, hiding the decks, hands and events.
+    pub fn public_view(&self) -> PublicBoard {
+        PublicBoard {
+            tiles: self.tiles.clone(),
+            shape: self.shape.clone(),
+            time: self.time.clone(),
+            players: self.players.iter().map(PublicPlayer::from).collect(),
+            cards: self.cards.clone(),
+            dead_pieces: self.dead_pieces.clone(),
+        }
+    }
+
+    /// Same as `public_view`, but the player of the given color can see its own hand.
+    pub fn view_for(&self, color: &Color) -> PublicBoard {
+        let mut view = self.public_view();
+        view.players
+            .iter_mut()
+            .zip(self.players.iter())
+            .filter(|(_, player)| &player.color == color)
+            .for_each(|(public, player)| public.hand = Some(player.hand.clone()));
+        view
+    }
+
     pub fn get_piece(&self, pos: &Pos) -> Option<&Piece> {
         self.get(pos).map(|tile| &tile.piece)
     }
@@ -1307,6 +1747,84 @@ impl Default for Board {
             rng: BoardRng::default(),
             events: Events::default(),
             time: Time::default(),
+            max_rounds: None,
+            stats: GameStats::default(),
+        }
+    }
+}
+
+/// The public information of a `Player`, only the sizes of its hand and deck are shown.
+/// The hand is only present in the view of its own player.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct PublicPlayer {
+    pub id: usize,
+    pub color: Color,
+    pub mana: Mana,
+    pub movements: Movements,
+    pub hand_size: usize,
+    pub deck_size: usize,
+    pub hand: Option<Cards>,
+}
+
+impl From<&Player> for PublicPlayer {
+    fn from(player: &Player) -> Self {
+        PublicPlayer {
+            id: player.id,
+            color: player.color.clone(),
+            mana: player.mana.clone(),
+            movements: player.movements.clone(),
+            hand_size: player.hand.len(),
+            deck_size: player.deck.len(),
+            hand: None,
+        }
+    }
+}
+
+/// The part of a `Board` that can be sent to any client.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct PublicBoard {
+    pub tiles: Vec<Tile>,
+    pub shape: Shape,
+    pub time: Time,
+    pub players: Vec<PublicPlayer>,
+    pub cards: Cards,
+    pub dead_pieces: Vec<Piece>,
+}
+
+/// Deserialized form of `Board`, whose tiles come without their positions.
+#[derive(Deserialize)]
+struct BoardData {
+    tiles: Vec<Tile>,
+    rng: BoardRng,
+    time: Time,
+    #[serde(default)]
+    max_rounds: Option<usize>,
+    players: Vec<Player>,
+    cards: Cards,
+    dead_pieces: Vec<Piece>,
+    shape: Shape,
+    events: Events,
+    #[serde(default)]
+    stats: GameStats,
+}
+
+impl From<BoardData> for Board {
+    fn from(mut data: BoardData) -> Self {
+        data.tiles
+            .iter_mut()
+            .zip(data.shape.points_iter())
+            .for_each(|(tile, pos)| tile.pos = pos);
+        Board {
+            tiles: data.tiles,
+            rng: data.rng,
+            time: data.time,
+            max_rounds: data.max_rounds,
+            players: data.players,
+            cards: data.cards,
+            dead_pieces: data.dead_pieces,
+            shape: data.shape,
+            events: data.events,
+            stats: data.stats,
         }
     }
 }
@@ -1623,6 +2141,13 @@ pub enum EventFunctionError {
     EmptyDeck,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub enum BoardError {
+    OutOfBoard,
+    OccupiedTile,
+    NoDeadPiece,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub enum FilterFunction {
     Square(usize),
@@ -1683,3 +2208,1023 @@ impl FilterFunction {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ability::{self, Ability},
+        Direction, Info, PaladinAbilityType, SubDirection,
+    };
+
+    fn catapult_board() -> Board {
+        let mut board = Board::default();
+        board
+            .get_mut(&Pos::new(3, 3))
+            .unwrap()
+            .replace(Piece::catapult(Color::White));
+        board
+            .get_mut(&Pos::new(4, 3))
+            .unwrap()
+            .replace(Piece::pawn(Color::White));
+        board
+            .get_mut(&Pos::new(2, 3))
+            .unwrap()
+            .replace(Piece::rook(Color::White));
+        board
+    }
+
+    #[test]
+    fn catapults_launch_pawns_but_not_structures() {
+        let board = catapult_board();
+        let catapult = Pos::new(3, 3);
+        assert!(board.can_transport(&catapult, &Pos::new(4, 3)));
+        assert!(!board.can_transport(&catapult, &Pos::new(2, 3)));
+        assert!(!board.can_transport(&Pos::new(4, 3), &catapult));
+
+        let launch = |subdir| {
+            Info::Trio(
+                Box::new(Info::Direction(Direction::N)),
+                Box::new(Info::SubDirection(subdir)),
+                Box::new(Info::Integer(2)),
+            )
+        };
+        assert!(ability::Catapult::can_use(
+            &board,
+            &catapult,
+            &launch(SubDirection::E)
+        ));
+        assert!(!ability::Catapult::can_use(
+            &board,
+            &catapult,
+            &launch(SubDirection::W)
+        ));
+    }
+
+    #[test]
+    fn cargo_moves_with_the_carrier() {
+        let mut board = catapult_board();
+        assert!(!board.transport(&Pos::new(3, 3), &Pos::new(2, 3), &Pos::new(3, 5)));
+        // the pawn would land on the rook
+        assert!(!board.transport(&Pos::new(3, 3), &Pos::new(4, 3), &Pos::new(1, 3)));
+
+        assert!(board.transport(&Pos::new(3, 3), &Pos::new(4, 3), &Pos::new(3, 5)));
+        assert!(board.is_empty(&Pos::new(3, 3)));
+        assert!(board.is_empty(&Pos::new(4, 3)));
+        assert_eq!(board.get_piece(&Pos::new(3, 5)).unwrap().name(), "Catapult");
+        assert_eq!(board.get_piece(&Pos::new(4, 5)).unwrap().name(), "Pawn");
+
+        // sliding onto the tile the cargo leaves
+        assert!(board.transport(&Pos::new(3, 5), &Pos::new(4, 5), &Pos::new(4, 5)));
+        assert_eq!(board.get_piece(&Pos::new(4, 5)).unwrap().name(), "Catapult");
+        assert_eq!(board.get_piece(&Pos::new(5, 5)).unwrap().name(), "Pawn");
+    }
+
+    #[test]
+    fn row_and_column_major_orders() {
+        let shape = Shape::new(vec![shape::Square {
+            anchor: Pos::new(0, 0),
+            width: 2,
+            height: 3,
+        }]);
+        let column_major = shape.points_iter().collect::<Vec<_>>();
+        let row_major = shape.points_row_major().collect::<Vec<_>>();
+        assert_ne!(column_major, row_major);
+        assert_eq!(
+            row_major,
+            vec![
+                Pos::new(0, 0),
+                Pos::new(1, 0),
+                Pos::new(0, 1),
+                Pos::new(1, 1),
+                Pos::new(0, 2),
+                Pos::new(1, 2),
+            ]
+        );
+        let mut sorted = column_major.clone();
+        sorted.sort_by_key(|pos| (pos.y, pos.x));
+        assert_eq!(sorted, row_major);
+
+        let cross = Shape::cross_shape();
+        assert_eq!(
+            cross.points_row_major().count(),
+            cross.points_iter().count()
+        );
+    }
+
+    #[test]
+    fn cchessboard_columns() {
+        let board = Board::cchessboard();
+        assert_eq!(board.width(), 16);
+        assert_eq!(board.height(), 17);
+        let column = board.column_iter(3).collect::<Vec<_>>();
+        assert_eq!(column.len(), 17);
+        assert!(column
+            .iter()
+            .enumerate()
+            .all(|(y, tile)| tile.pos() == &Pos::new(3, y)));
+        assert_eq!(board.column_iter(16).count(), 0);
+    }
+
+    #[test]
+    fn several_movements_per_turn() {
+        let mut board = Board::cchessboard();
+        board.set_movements_per_turn(Movements(3));
+        for movement in 1..3 {
+            board.tick();
+            assert_eq!(board.time, Time::new(0, 0, movement));
+            assert_eq!(board.current_player().color(), &Color::White);
+        }
+        board.tick();
+        assert_eq!(board.time, Time::new(0, 1, 0));
+        assert_eq!(board.current_player().color(), &Color::Black);
+
+        let player = Player::new(Color::White, 0, Cards::default()).with_movements(Movements(0));
+        assert_eq!(player.movements, Movements(0));
+        board.set_movements_per_turn(Movements(0));
+        board.tick();
+        assert_eq!(board.time, Time::new(1, 0, 0));
+    }
+
+    #[test]
+    fn skipping_movements_passes_the_turn() {
+        let mut board = Board::default_chessboard();
+        assert!(board.skip_movement());
+        assert_eq!(board.current_player().color(), &Color::Black);
+        assert!(board.skip_movement());
+        assert_eq!(board.time, Time::new(1, 0, 0));
+
+        board.max_rounds = Some(1);
+        assert!(!board.skip_movement());
+        assert_eq!(board.time, Time::new(1, 0, 0));
+    }
+
+    #[test]
+    fn stats_count_the_made_actions() {
+        let mut board = Board::default();
+        let (rook, pawn, bishop, queen) = (
+            Pos::new(0, 0),
+            Pos::new(0, 4),
+            Pos::new(5, 5),
+            Pos::new(5, 0),
+        );
+        for (pos, piece) in [
+            (&rook, Piece::rook(Color::White)),
+            (&pawn, Piece::pawn(Color::Black)),
+            (&bishop, Piece::bishop(Color::White)),
+            (&queen, Piece::queen(Color::Black)),
+        ] {
+            board.get_mut(pos).unwrap().replace(piece);
+        }
+
+        assert!(board.make(Action::take(&rook, &pawn)));
+        assert!(board.make(Action::ability(&bishop, Info::Direction(Direction::N))));
+        // the heroic queen refuses the attack, which isn't counted
+        assert!(!board.make(Action::attack(&pawn, &queen)));
+        assert_eq!(
+            board.stats(),
+            &GameStats {
+                moves: 0,
+                takes: 1,
+                attacks: 0,
+                abilities: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn empty_and_occupied_tiles() {
+        let board = Board::default_chessboard();
+        assert_eq!(board.empty_tiles().count(), 32);
+        assert_eq!(board.occupied_tiles().count(), 32);
+        assert!(board
+            .empty_tiles()
+            .all(|tile| (2..6).contains(&tile.pos().y)));
+    }
+
+    #[test]
+    fn default_chessboard_is_symmetric() {
+        let board = Board::default_chessboard();
+        for pos in board.shape().points_iter() {
+            let mirrored = Pos::new(pos.x, board.height() - 1 - pos.y);
+            let piece = &board.get(&pos).unwrap().piece;
+            let other = &board.get(&mirrored).unwrap().piece;
+            assert_eq!(piece.name(), other.name(), "{:?}", pos);
+            assert_eq!(piece.color().map(Color::opposite), other.color().cloned());
+        }
+        assert_eq!(board.pieces(&Color::White).count(), 16);
+        assert!(matches!(
+            board.get(&Pos::new(4, 7)).unwrap().piece,
+            Piece::King(_)
+        ));
+    }
+
+    #[test]
+    fn ray_iter_stops_at_first_piece() {
+        let mut board = Board::default();
+        board
+            .get_mut(&Pos::new(0, 3))
+            .unwrap()
+            .replace(Piece::pawn(Color::White));
+        board
+            .get_mut(&Pos::new(0, 5))
+            .unwrap()
+            .replace(Piece::pawn(Color::Black));
+
+        let ray: Vec<&Pos> = board
+            .ray_iter(&Pos::new(0, 0), (0, 1))
+            .map(|tile| tile.pos())
+            .collect();
+        assert_eq!(ray, vec![&Pos::new(0, 1), &Pos::new(0, 2), &Pos::new(0, 3)]);
+
+        let ray: Vec<&Pos> = board
+            .ray_iter(&Pos::new(0, 0), (1, 1))
+            .map(|tile| tile.pos())
+            .collect();
+        assert_eq!(ray.len(), 7);
+        assert_eq!(ray.last(), Some(&&Pos::new(7, 7)));
+
+        assert_eq!(board.ray_iter(&Pos::new(0, 0), (-1, 0)).count(), 0);
+        assert_eq!(board.ray_iter(&Pos::new(0, 0), (0, 0)).count(), 0);
+    }
+
+    #[test]
+    fn tile_pos_survives_serialization() {
+        let mut board = Board::with_shape(Shape::cross_shape());
+        board
+            .get_mut(&Pos::new(0, 3))
+            .unwrap()
+            .replace(Piece::pawn(Color::White));
+        let json = serde_json::to_string(&board).unwrap();
+        assert!(!json.contains("\"pos\""));
+
+        let board: Board = serde_json::from_str(&json).unwrap();
+        for (tile, pos) in board.iter().zip(board.shape().points_iter()) {
+            assert_eq!(tile.pos(), &pos);
+            assert_eq!(board.get(&pos).unwrap().pos(), &pos);
+        }
+        assert!(board.get(&Pos::new(0, 3)).unwrap().has_pawn());
+        assert!(board.get(&Pos::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn paladin_dispels_ice() {
+        let mut board = Board::default();
+        let (paladin, frozen) = (Pos::new(3, 3), Pos::new(4, 4));
+        board
+            .get_mut(&paladin)
+            .unwrap()
+            .replace(Piece::paladin(Color::White));
+        board
+            .get_mut(&frozen)
+            .unwrap()
+            .replace(Piece::pawn(Color::White));
+        let data = board.get_mut_data(&frozen).unwrap();
+        data.add_effect(Effect::ice());
+        data.add_effect(Effect::invulnerability());
+
+        let dispel = Info::PaladinAbilityInfo(PaladinAbilityType::Dispel(frozen.clone()));
+        assert!(!ability::Paladin::can_use(&board, &paladin, &dispel));
+        board.cards.add(Card::Dispel);
+        assert!(ability::Paladin::can_use(&board, &paladin, &dispel));
+
+        ability::Paladin::r#use(&mut board, &paladin, dispel);
+        let data = board.get_data(&frozen).unwrap();
+        assert_eq!(data.effects.0, vec![Effect::invulnerability()]);
+
+        board.clear_effects(&frozen);
+        assert!(board.get_data(&frozen).unwrap().effects.0.is_empty());
+    }
+
+    #[test]
+    fn taking_a_demonic_piece_gives_mana_once() {
+        let mut board = Board::default();
+        let (rook, warlock) = (Pos::new(0, 0), Pos::new(0, 3));
+        board
+            .get_mut(&rook)
+            .unwrap()
+            .replace(Piece::rook(Color::White));
+        board
+            .get_mut(&warlock)
+            .unwrap()
+            .replace(Piece::warlock(Color::Black));
+
+        assert!(board.make(Action::take(&rook, &warlock)));
+        let black = board.player_from_color(&Color::Black).unwrap();
+        assert_eq!(black.mana, DEMONIC_MANA);
+        assert!(matches!(board.get_piece(&warlock), Some(Piece::Rook(_))));
+    }
+
+    #[test]
+    fn burning_pawn_dies_when_the_fire_goes_out() {
+        let mut board = Board::default();
+        let pawn = Pos::new(3, 3);
+        board
+            .get_mut(&pawn)
+            .unwrap()
+            .replace(Piece::pawn(Color::White));
+        board
+            .get_mut_data(&pawn)
+            .unwrap()
+            .add_effect(Effect::Fire(Time::rounds(2)));
+
+        // two players with a movement each, a round every two ticks
+        for _ in 0..3 {
+            board.tick();
+        }
+        assert!(board.has_piece(&pawn));
+        board.tick();
+        assert!(board.is_empty(&pawn));
+        assert_eq!(board.dead_pieces, vec![Piece::pawn(Color::White)]);
+    }
+
+    #[test]
+    fn burning_demonic_piece_gives_mana() {
+        let mut board = Board::default();
+        let warlock = Pos::new(3, 3);
+        board
+            .get_mut(&warlock)
+            .unwrap()
+            .replace(Piece::warlock(Color::Black));
+        // the same board without the fire, for the mana earned over the round
+        let mut unburnt = board.clone();
+        board
+            .get_mut_data(&warlock)
+            .unwrap()
+            .add_effect(Effect::Fire(Time::rounds(1)));
+
+        for board in [&mut board, &mut unburnt] {
+            board.tick();
+            board.tick();
+        }
+        assert!(board.is_empty(&warlock));
+        let mana = |board: &Board| board.player_from_color(&Color::Black).unwrap().mana.clone();
+        let mut expected = mana(&unburnt);
+        expected += DEMONIC_MANA;
+        assert_eq!(mana(&board), expected);
+    }
+
+    #[test]
+    fn invulnerable_piece_survives_the_fire() {
+        let mut board = Board::default();
+        let pawn = Pos::new(3, 3);
+        board
+            .get_mut(&pawn)
+            .unwrap()
+            .replace(Piece::pawn(Color::White));
+        let data = board.get_mut_data(&pawn).unwrap();
+        data.add_effect(Effect::Fire(Time::rounds(1)));
+        data.add_effect(Effect::invulnerability());
+
+        board.tick();
+        board.tick();
+        assert!(board.has_piece(&pawn));
+        assert!(board.dead_pieces.is_empty());
+    }
+
+    #[test]
+    fn iced_rook_cannot_move() {
+        let mut board = Board::default();
+        let rook = Pos::new(0, 0);
+        board
+            .get_mut(&rook)
+            .unwrap()
+            .replace(Piece::rook(Color::White));
+        board.get_mut_data(&rook).unwrap().add_effect(Effect::ice());
+
+        for _ in 0..5 {
+            assert!(board.actions_from(&rook).is_empty());
+            board.tick();
+        }
+        assert!(board.actions_from(&rook).is_empty());
+        board.tick();
+        assert!(board.get_data(&rook).unwrap().effects.0.is_empty());
+        assert!(!board.actions_from(&rook).is_empty());
+    }
+
+    #[test]
+    fn make_calls_on_do_on_the_actor() {
+        let mut board = Board::default();
+        let (knight, cannon, queen) = (Pos::new(1, 0), Pos::new(0, 0), Pos::new(0, 3));
+        board
+            .get_mut(&knight)
+            .unwrap()
+            .replace(Piece::knight(Color::White));
+        board
+            .get_mut(&cannon)
+            .unwrap()
+            .replace(Piece::cannon(Color::White));
+        board
+            .get_mut(&queen)
+            .unwrap()
+            .replace(Piece::queen(Color::Black));
+        let moved = |board: &Board, pos| board.get_data(pos).unwrap().moved;
+        let calls = |board: &Board, pos| {
+            board
+                .get_data(pos)
+                .unwrap()
+                .effects
+                .0
+                .iter()
+                .find_map(|effect| match effect {
+                    Effect::Counter(_, calls) => Some(*calls),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        let counter = || Effect::Counter(Time::rounds(10), 0);
+        board.get_mut_data(&knight).unwrap().add_effect(counter());
+        board.get_mut_data(&cannon).unwrap().add_effect(counter());
+
+        let to = Pos::new(2, 2);
+        assert!(board.make(Action::r#move(&knight, &to)));
+        assert!(moved(&board, &to));
+        assert_eq!(calls(&board, &to), 1);
+        // the heroic queen refuses the attack, so the cannon did nothing
+        assert!(!board.make(Action::attack(&cannon, &queen)));
+        assert!(!moved(&board, &cannon));
+        assert!(!moved(&board, &queen));
+        assert_eq!(calls(&board, &cannon), 0);
+
+        // the teleported king is marked on the tile it lands on
+        let (king, landing) = (Pos::new(5, 5), Pos::new(7, 7));
+        board
+            .get_mut(&king)
+            .unwrap()
+            .replace(Piece::king(Color::White));
+        board.get_mut_data(&king).unwrap().add_effect(counter());
+        board.mut_current_player().mana = Mana(2);
+        assert!(board.make(Action::ability(&king, Info::Pos(landing.clone()))));
+        assert!(board.is_empty(&king));
+        assert!(moved(&board, &landing));
+        assert_eq!(calls(&board, &landing), 1);
+
+        board.tick();
+        assert!(!moved(&board, &to));
+    }
+
+    #[test]
+    fn magic_tiles_on_custom_shapes() {
+        assert_eq!(
+            Board::cchessboard().symmetric_magic(),
+            vec![
+                Pos::new(0, 7),
+                Pos::new(15, 7),
+                Pos::new(0, 9),
+                Pos::new(15, 9)
+            ]
+        );
+
+        let mut board = Board::with_shape(Shape::cross_shape());
+        let magic = board.symmetric_magic();
+        assert_eq!(
+            magic,
+            vec![
+                Pos::new(0, 3),
+                Pos::new(7, 3),
+                Pos::new(0, 4),
+                Pos::new(7, 4)
+            ]
+        );
+        board.set_magic(&magic);
+        let marked = board
+            .tiles
+            .iter()
+            .filter(|tile| tile.magic)
+            .map(|tile| tile.pos().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(marked.len(), 4);
+        assert!(marked.iter().all(|pos| magic.contains(pos)));
+
+        let warlock = Pos::new(1, 3);
+        board
+            .get_mut(&warlock)
+            .unwrap()
+            .replace(Piece::warlock(Color::White));
+        let info = Info::Integer(0);
+        assert!(ability::Warlock::can_use(&board, &warlock, &info));
+        board.set_magic(&[Pos::new(5, 5)]);
+        assert!(!ability::Warlock::can_use(&board, &warlock, &info));
+    }
+
+    #[test]
+    fn abilities_cost_mana_and_set_a_cooldown() {
+        let mut board = Board::default();
+        let (king, to) = (Pos::new(3, 3), Pos::new(5, 5));
+        board
+            .get_mut(&king)
+            .unwrap()
+            .replace(Piece::king(Color::White));
+        let teleport = Action::ability(&king, Info::Pos(to.clone()));
+
+        board.mut_current_player().mana = Mana(1);
+        assert!(!board.can_afford_ability(&king));
+        assert!(!board.make(teleport.clone()));
+        assert!(board.has_piece(&king));
+        assert_eq!(board.current_player().mana, Mana(1));
+
+        // an unusable ability is refused before anything is paid
+        board.mut_current_player().mana = Mana(2);
+        assert!(!board.make(Action::ability(&king, Info::Pos(king.clone()))));
+        assert!(board.has_piece(&king));
+        assert_eq!(board.current_player().mana, Mana(2));
+        assert_eq!(board.get_data(&king).unwrap().cooldown, Time::default());
+
+        assert!(board.make(teleport));
+        assert!(board.is_empty(&king));
+        assert_eq!(board.current_player().mana, Mana(0));
+        let cooldown = Piece::king(Color::White)
+            .ability_description()
+            .unwrap()
+            .cooldown;
+        assert_eq!(board.get_data(&to).unwrap().cooldown, cooldown);
+
+        let bishop = Pos::new(0, 0);
+        board
+            .get_mut(&bishop)
+            .unwrap()
+            .replace(Piece::bishop(Color::White));
+        assert!(board.make(Action::ability(&bishop, Info::Direction(Direction::N))));
+        let bishop = Pos::new(0, 1);
+        assert_eq!(board.get_data(&bishop).unwrap().cooldown, Time::rounds(2));
+        assert!(!board.make(Action::ability(&bishop, Info::Direction(Direction::N))));
+    }
+
+    #[test]
+    fn builders_only_wall_buildable_tiles() {
+        let mut board = Board::default();
+        let builder = Pos::new(3, 3);
+        board
+            .get_mut(&builder)
+            .unwrap()
+            .replace(Piece::builder(Color::White));
+        let north = Info::Direction(Direction::N);
+        let (n, ne, nw) = (Pos::new(3, 4), Pos::new(4, 4), Pos::new(2, 4));
+        assert!(board.set_buildable(&n, false));
+        assert!(!board.set_buildable(&Pos::new(8, 8), false));
+
+        assert!(ability::Builder::can_use(&board, &builder, &north));
+        ability::Builder::r#use(&mut board, &builder, north.clone());
+        assert!(board.is_empty(&n));
+        assert!(matches!(board.get_piece(&ne), Some(Piece::Wall(_))));
+        assert!(matches!(board.get_piece(&nw), Some(Piece::Wall(_))));
+
+        board.get_mut(&ne).unwrap().remove();
+        board.get_mut(&nw).unwrap().remove();
+        board.set_buildable(&ne, false);
+        board.set_buildable(&nw, false);
+        assert!(!ability::Builder::can_use(&board, &builder, &north));
+    }
+
+    #[test]
+    fn paladin_revives_the_last_dead_of_its_color() {
+        let mut board = Board::default();
+        let paladin = Pos::new(3, 3);
+        board
+            .get_mut(&paladin)
+            .unwrap()
+            .replace(Piece::paladin(Color::White));
+        let victims = [
+            Piece::knight(Color::White),
+            Piece::rook(Color::Black),
+            Piece::bishop(Color::White),
+            Piece::pawn(Color::Black),
+        ];
+        for victim in victims {
+            let pos = Pos::new(6, 6);
+            board.get_mut(&pos).unwrap().replace(victim);
+            board.attack_piece(&paladin, &pos);
+        }
+        board.cards.add(Card::Revive);
+
+        for (to, revived) in [
+            (Pos::new(2, 2), Piece::bishop(Color::White)),
+            (Pos::new(2, 3), Piece::knight(Color::White)),
+        ] {
+            let revive = Info::PaladinAbilityInfo(PaladinAbilityType::Revive(to.clone()));
+            assert!(ability::Paladin::can_use(&board, &paladin, &revive));
+            ability::Paladin::r#use(&mut board, &paladin, revive);
+            assert_eq!(board.get_piece(&to), Some(&revived));
+        }
+        assert_eq!(
+            board.dead_pieces,
+            vec![Piece::rook(Color::Black), Piece::pawn(Color::Black)]
+        );
+        assert_eq!(
+            board.remove_last_dead_with_color(&Color::White),
+            Piece::None
+        );
+    }
+
+    #[test]
+    fn attacks_spare_heroic_and_invulnerable_pieces() {
+        let mut board = Board::default();
+        let (cannon, queen, pawn) = (Pos::new(0, 0), Pos::new(0, 3), Pos::new(3, 0));
+        board
+            .get_mut(&cannon)
+            .unwrap()
+            .replace(Piece::cannon(Color::White));
+        board
+            .get_mut(&queen)
+            .unwrap()
+            .replace(Piece::queen(Color::Black));
+        board
+            .get_mut(&pawn)
+            .unwrap()
+            .replace(Piece::pawn(Color::Black));
+        board
+            .get_mut_data(&pawn)
+            .unwrap()
+            .add_effect(Effect::invulnerability());
+
+        assert!(!board.attack_piece(&cannon, &queen));
+        assert!(!board.attack_piece(&cannon, &pawn));
+        assert!(!board.attack_piece(&cannon, &Pos::new(5, 5)));
+        assert_eq!(board.get_piece(&queen), Some(&Piece::queen(Color::Black)));
+        assert!(matches!(board.get_piece(&pawn), Some(Piece::Pawn(_))));
+        assert!(board.dead_pieces.is_empty());
+
+        board.clear_effects(&pawn);
+        assert!(board.attack_piece(&cannon, &pawn));
+        assert!(board.is_empty(&pawn));
+    }
+
+    #[test]
+    fn ship_broadside_spares_heroic_pieces() {
+        let mut board = Board::default();
+        let ship = Pos::new(3, 3);
+        board
+            .get_mut(&ship)
+            .unwrap()
+            .replace(Piece::ship(Color::White));
+        let area = board
+            .get_piece(&ship)
+            .unwrap()
+            .ability_area(&board, &ship, &Info::Integer(0));
+        board
+            .get_mut(&area[0])
+            .unwrap()
+            .replace(Piece::queen(Color::Black));
+        board
+            .get_mut(&area[1])
+            .unwrap()
+            .replace(Piece::rook(Color::Black));
+
+        ability::Ship::r#use(&mut board, &ship, Info::Integer(0));
+        assert_eq!(board.get_piece(&area[0]), Some(&Piece::queen(Color::Black)));
+        assert!(board.is_empty(&area[1]));
+        assert_eq!(board.dead_pieces, vec![Piece::rook(Color::Black)]);
+    }
+
+    #[test]
+    fn abilities_cannot_kill_immune_pieces() {
+        let mut board = Board::default();
+        let (ram, paladin, magician) = (Pos::new(3, 0), Pos::new(2, 3), Pos::new(3, 3));
+        board
+            .get_mut(&ram)
+            .unwrap()
+            .replace(Piece::ram(Color::White));
+        board
+            .get_mut(&paladin)
+            .unwrap()
+            .replace(Piece::paladin(Color::White));
+        board
+            .get_mut(&magician)
+            .unwrap()
+            .replace(Piece::magician(Color::Black));
+
+        ability::Ram::r#use(&mut board, &ram, Info::Direction(Direction::N));
+        assert!(matches!(
+            board.get_piece(&magician),
+            Some(Piece::Magician(_))
+        ));
+        assert!(matches!(
+            board.get_piece(&Pos::new(3, 2)),
+            Some(Piece::Ram(_))
+        ));
+
+        board.cards.add(Card::AttackDemonic);
+        let attack = Info::PaladinAbilityInfo(PaladinAbilityType::Attack(magician.clone()));
+        assert!(!ability::Paladin::can_use(&board, &paladin, &attack));
+        ability::Paladin::r#use(&mut board, &paladin, attack);
+        assert!(matches!(
+            board.get_piece(&magician),
+            Some(Piece::Magician(_))
+        ));
+        assert!(board.dead_pieces.is_empty());
+
+        // pieces that aren't immune fall to the attack, demonic or not
+        let rook = Pos::new(2, 5);
+        board
+            .get_mut(&rook)
+            .unwrap()
+            .replace(Piece::rook(Color::Black));
+        let attack = Info::PaladinAbilityInfo(PaladinAbilityType::Attack(rook.clone()));
+        assert!(ability::Paladin::can_use(&board, &paladin, &attack));
+        ability::Paladin::r#use(&mut board, &paladin, attack);
+        assert!(board.is_empty(&rook));
+        board.dead_pieces.clear();
+
+        // the warlock is immune but demonic, the paladin's card is made for it
+        let warlock = Pos::new(5, 5);
+        board
+            .get_mut(&warlock)
+            .unwrap()
+            .replace(Piece::warlock(Color::Black));
+        assert!(!board.ability_attack_piece(&paladin, &warlock));
+        let attack = Info::PaladinAbilityInfo(PaladinAbilityType::Attack(warlock.clone()));
+        assert!(ability::Paladin::can_use(&board, &paladin, &attack));
+        ability::Paladin::r#use(&mut board, &paladin, attack);
+        assert!(board.is_empty(&warlock));
+        assert_eq!(board.dead_pieces, vec![Piece::warlock(Color::Black)]);
+    }
+
+    #[test]
+    fn heroic_queen_can_be_taken_but_not_attacked() {
+        let mut board = Board::default();
+        let (archer, rook, queen) = (Pos::new(3, 1), Pos::new(0, 3), Pos::new(3, 3));
+        board
+            .get_mut(&archer)
+            .unwrap()
+            .replace(Piece::archer(Color::White));
+        board
+            .get_mut(&rook)
+            .unwrap()
+            .replace(Piece::rook(Color::White));
+        board
+            .get_mut(&queen)
+            .unwrap()
+            .replace(Piece::queen(Color::Black));
+
+        assert!(!board
+            .actions_from(&archer)
+            .iter()
+            .any(|action| action.is_attack()));
+        assert!(!board.make(Action::attack(&archer, &queen)));
+        assert!(matches!(board.get_piece(&queen), Some(Piece::Queen(_))));
+
+        assert!(board.make(Action::take(&rook, &queen)));
+        assert!(matches!(board.get_piece(&queen), Some(Piece::Rook(_))));
+        assert!(board.is_empty(&rook));
+
+        board
+            .get_mut(&rook)
+            .unwrap()
+            .replace(Piece::pawn(Color::Black));
+        board
+            .get_mut_data(&rook)
+            .unwrap()
+            .add_effect(Effect::invulnerability());
+        assert!(!board.take_piece(&queen, &rook));
+        assert!(matches!(board.get_piece(&queen), Some(Piece::Rook(_))));
+    }
+
+    #[test]
+    fn resurrect_the_dead() {
+        let mut board = Board::default();
+        let (to, occupied) = (Pos::new(2, 2), Pos::new(0, 0));
+        board
+            .get_mut(&occupied)
+            .unwrap()
+            .replace(Piece::rook(Color::White));
+        assert_eq!(
+            board.resurrect(&Color::White, &to),
+            Err(BoardError::NoDeadPiece)
+        );
+
+        let mut knight = Piece::knight(Color::White);
+        knight.mut_data().unwrap().add_effect(Effect::ice());
+        board.dead_pieces.push(knight);
+        board.dead_pieces.push(Piece::bishop(Color::Black));
+        assert_eq!(
+            board.resurrect(&Color::White, &occupied),
+            Err(BoardError::OccupiedTile)
+        );
+        assert_eq!(
+            board.resurrect(&Color::White, &Pos::new(20, 20)),
+            Err(BoardError::OutOfBoard)
+        );
+        assert_eq!(board.resurrect(&Color::White, &to), Ok(()));
+        assert_eq!(board.get_piece(&to), Some(&Piece::knight(Color::White)));
+        assert_eq!(board.dead_pieces, vec![Piece::bishop(Color::Black)]);
+    }
+
+    fn play(board: &mut Board, moves: &[(Pos, Pos)]) {
+        for (from, to) in moves {
+            board.make(Action::r#move(from, to));
+            board.tick();
+        }
+    }
+
+    #[test]
+    fn demonic_pieces_give_mana_when_killed() {
+        let mut board = Board::default();
+        let (cannon, warlock, rook) = (Pos::new(0, 0), Pos::new(0, 3), Pos::new(5, 5));
+        board
+            .get_mut(&cannon)
+            .unwrap()
+            .replace(Piece::cannon(Color::White));
+        board
+            .get_mut(&warlock)
+            .unwrap()
+            .replace(Piece::warlock(Color::Black));
+        board
+            .get_mut(&rook)
+            .unwrap()
+            .replace(Piece::rook(Color::Black));
+        let mana = |board: &Board, color| board.player_from_color(&color).unwrap().mana.clone();
+
+        board.make(Action::attack(&cannon, &warlock));
+        assert_eq!(mana(&board, Color::Black), DEMONIC_MANA);
+        assert_eq!(mana(&board, Color::White), Mana(0));
+
+        board.make(Action::attack(&cannon, &rook));
+        assert_eq!(mana(&board, Color::Black), DEMONIC_MANA);
+    }
+
+    #[test]
+    fn transpositions_hash_equal() {
+        let start = Board::default_chessboard();
+        let (w1, w2, b1) = (
+            (Pos::new(1, 0), Pos::new(2, 2)),
+            (Pos::new(6, 0), Pos::new(5, 2)),
+            (Pos::new(1, 7), Pos::new(2, 5)),
+        );
+        let mut first = start.clone();
+        play(&mut first, &[w1.clone(), b1.clone(), w2.clone()]);
+        let mut second = start.clone();
+        play(&mut second, &[w2, b1.clone(), w1.clone()]);
+        assert_eq!(first.position_hash(), second.position_hash());
+        assert_ne!(first.position_hash(), start.position_hash());
+
+        // back where it started, a round later
+        let mut shuffled = start.clone();
+        play(
+            &mut shuffled,
+            &[w1.clone(), b1.clone(), (w1.1, w1.0), (b1.1, b1.0)],
+        );
+        assert_ne!(shuffled.time, start.time);
+        assert_eq!(shuffled.position_hash(), start.position_hash());
+    }
+
+    #[test]
+    fn public_view_hides_deck() {
+        let mut board = Board::cchessboard();
+        board
+            .mut_player_from_color(&Color::White)
+            .unwrap()
+            .deck
+            .add(Card::Revive);
+        let view = board.public_view();
+        let white = view
+            .players
+            .iter()
+            .find(|p| p.color == Color::White)
+            .unwrap();
+        assert_eq!(white.deck_size, 4);
+        assert_eq!(white.hand_size, 1);
+
+        let json = serde_json::to_string(&view).unwrap();
+        assert!(!json.contains("deck\""));
+        assert!(!json.contains("AddMovement"));
+        assert!(!json.contains("Revive"));
+    }
+
+    #[test]
+    fn view_for_hides_opponent_hand() {
+        let mut board = Board::cchessboard();
+        board
+            .mut_player_from_color(&Color::Black)
+            .unwrap()
+            .hand
+            .add(Card::Revive);
+        let view = board.view_for(&Color::White);
+        let white = view
+            .players
+            .iter()
+            .find(|p| p.color == Color::White)
+            .unwrap();
+        let black = view
+            .players
+            .iter()
+            .find(|p| p.color == Color::Black)
+            .unwrap();
+        assert_eq!(white.hand, Some(Cards(vec![Card::AddMovement])));
+        assert_eq!(black.hand, None);
+        assert_eq!(black.hand_size, 2);
+
+        let json = serde_json::to_string(&view).unwrap();
+        assert!(json.contains("AddMovement"));
+        assert!(!json.contains("Revive"));
+    }
+
+    #[test]
+    fn flipped_display() {
+        let board = Board::default();
+        assert_eq!(board.display_rows(false), vec![7, 6, 5, 4, 3, 2, 1, 0]);
+        assert_eq!(board.display_rows(true), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+
+        let row: Vec<usize> = board
+            .display_row(2, false)
+            .iter()
+            .map(|t| t.pos().x)
+            .collect();
+        assert_eq!(row, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        let row: Vec<usize> = board
+            .display_row(2, true)
+            .iter()
+            .map(|t| t.pos().x)
+            .collect();
+        assert_eq!(row, vec![7, 6, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn get_many_keeps_order() {
+        let board = Board::with_shape(Shape::cross_shape());
+        let positions = [
+            Pos::new(7, 3),
+            Pos::new(0, 0),
+            Pos::new(3, 1),
+            Pos::new(20, 20),
+            Pos::new(0, 2),
+        ];
+        let tiles: Vec<Option<&Pos>> = board
+            .get_many(&positions)
+            .into_iter()
+            .map(|tile| tile.map(|t| t.pos()))
+            .collect();
+        assert_eq!(
+            tiles,
+            vec![
+                Some(&Pos::new(7, 3)),
+                None,
+                Some(&Pos::new(3, 1)),
+                None,
+                Some(&Pos::new(0, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn pieces_of_color() {
+        let board = Board::cchessboard();
+        let white: Vec<_> = board.pieces(&Color::White).collect();
+        assert_eq!(white.len(), 46);
+        assert!(white
+            .iter()
+            .all(|(_, piece)| piece.color() == Some(&Color::White)));
+
+        let cannons: Vec<_> = white
+            .iter()
+            .filter(|(_, piece)| matches!(piece, Piece::Cannon(_)))
+            .map(|(pos, _)| pos.clone())
+            .collect();
+        assert_eq!(cannons, [Pos::new(0, 0), Pos::new(15, 0)]);
+    }
+
+    #[test]
+    fn same_position_ignores_rng() {
+        let board = Board::default_chessboard();
+        let mut other = board.clone();
+        other.rng.next_movement();
+        assert!(board.same_position(&other));
+        assert_ne!(board, other);
+
+        other
+            .get_mut(&Pos::new(0, 2))
+            .unwrap()
+            .replace(Piece::pawn(Color::White));
+        assert!(!board.same_position(&other));
+    }
+
+    #[test]
+    fn draw_after_max_rounds() {
+        let mut board = Board {
+            max_rounds: Some(2),
+            ..Default::default()
+        };
+        let mut ticks = 0;
+        while !board.is_draw() && ticks < 100 {
+            board.tick();
+            ticks += 1;
+        }
+        assert!(board.is_draw());
+        assert_eq!(board.time.round, 2);
+
+        let time = board.time.clone();
+        board.tick();
+        assert_eq!(board.time, time);
+        assert!(!Board::default().is_draw());
+    }
+
+    #[test]
+    fn neighbors_stay_in_shape() {
+        let board = Board::default_chessboard();
+        let corner = Pos::new(0, 0);
+        assert_eq!(board.neighbors(&corner), [Pos::new(0, 1), Pos::new(1, 0)]);
+        assert_eq!(board.diagonal_neighbors(&corner), [Pos::new(1, 1)]);
+        assert_eq!(board.neighbors(&Pos::new(7, 7)).len(), 2);
+        assert_eq!(board.neighbors(&Pos::new(3, 4)).len(), 4);
+        assert_eq!(board.diagonal_neighbors(&Pos::new(3, 4)).len(), 4);
+    }
+}
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{board::Mana, Time};
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum Card {
     // Summon,
     Knight,
@@ -15,6 +15,7 @@ pub enum Card {
     AttackDemonic,
     Invulnerability,
     Revive,
+    Dispel,
     MoreMana,
     // Utility
     AddMovement,
@@ -32,6 +33,7 @@ impl Card {
             Card::AttackDemonic => 3,
             Card::Invulnerability => 5,
             Card::Revive => 4,
+            Card::Dispel => 2,
             Card::AddMovement => 1,
             Card::MoreMana => 2,
             Card::Mana => 1,
@@ -48,7 +50,7 @@ impl Card {
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct Cards(pub Vec<Card>);
 
 impl Cards {
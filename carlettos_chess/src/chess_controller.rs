@@ -8,9 +8,14 @@ use crate::{
         Board, Tile,
     },
     piece::Piece,
-    Action, Color, Pos,
+    Action, Color, Direction, Info, Pos,
 };
 
+/// The widest and tallest board `CChess::from_setup_code` accepts.
+pub const MAX_SETUP_SIDE: usize = 64;
+/// The most tiles `CChess::from_setup_code` accepts, counting the overlaps of the squares.
+pub const MAX_SETUP_TILES: usize = 1024;
+
 #[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CChess {
     pub board: Board,
@@ -26,61 +31,48 @@ impl CChess {
     /// This is a function to safely click on the board.
     /// if the position is not valid, it will make nothing.
     /// And it will use inner methods to handle the click.
+    /// Only the pieces of the current player can be selected and act, and nothing can once
+    /// there is a winner.
     /// Return true or false if the state had tried to change.
     pub fn click(&mut self, click_pos: Pos) -> bool {
         if !self.board.contains(&click_pos) {
             return false;
         }
 
-        let click_tile = match self.board.get(&click_pos) {
-            None => return false,
-            Some(tile) => tile,
-        };
+        if self.board.get(&click_pos).is_none() || self.board.winner().is_some() {
+            return false;
+        }
 
         match &self.selected {
             None => {
-                let piece = &click_tile.piece;
-                for other_pos in self.board.shape().points_iter() {
-                    if other_pos == click_pos {
-                        continue;
-                    }
-                    let move_action = Action::r#move(&click_pos, &other_pos);
-                    let take_action = Action::take(&click_pos, &other_pos);
-                    let attack_action = Action::attack(&click_pos, &other_pos);
-                    // TODO: add abilities
-                    if piece.can_do(&self.board, move_action) && self.board.is_empty(&other_pos) {
-                        self.moves.push(other_pos.clone());
-                    }
-                    if piece.can_do(&self.board, take_action)
-                        && self.board.has_piece(&other_pos)
-                        && !self.board.same_color(&click_pos, &other_pos)
-                    {
-                        self.takes.push(other_pos.clone());
-                    }
-                    if piece.can_do(&self.board, attack_action)
-                        && self.board.has_piece(&other_pos)
-                        && !self.board.same_color(&click_pos, &other_pos)
-                    {
-                        self.attacks.push(other_pos.clone());
+                if !self.is_owned(&click_pos) {
+                    return false;
+                }
+                for action in self.board.actions_from(&click_pos) {
+                    match action {
+                        Action::Move { to, .. } => self.moves.push(to),
+                        Action::Take { to, .. } => self.takes.push(to),
+                        Action::Attack { to, .. } => self.attacks.push(to),
+                        Action::Ability { .. } => (),
                     }
                 }
+                self.abilities = self
+                    .board
+                    .shape()
+                    .points_iter()
+                    .filter(|to| Action::ability_from_click(&self.board, &click_pos, to).is_some())
+                    .collect();
                 self.selected = Some(click_pos);
             }
+            Some(selected_pos) if !self.is_owned(selected_pos) => self.clear(),
             Some(selected_pos) => {
-                let mut tick = false;
-                if self.attacks.contains(&click_pos) {
-                    self.board.make(Action::attack(selected_pos, &click_pos));
-                    tick = true;
-                } else if self.takes.contains(&click_pos) {
-                    self.board.make(Action::take(selected_pos, &click_pos));
-                    tick = true;
-                } else if self.moves.contains(&click_pos) {
-                    self.board.make(Action::r#move(selected_pos, &click_pos));
-                    tick = true;
-                }
-                //TODO: handle ability
-                if tick {
-                    self.board.tick();
+                // the moves, takes and attacks go before the abilities aimed at the same tile
+                let action = Action::from_click(&self.board, selected_pos, &click_pos)
+                    .or_else(|| Action::ability_from_click(&self.board, selected_pos, &click_pos));
+                if let Some(action) = action {
+                    if self.board.make(action) {
+                        self.board.tick();
+                    }
                 }
                 self.clear();
             }
@@ -88,6 +80,128 @@ impl CChess {
         true
     }
 
+    /// Passes the current movement, dropping the selection, see `Board::skip_movement`.
+    pub fn skip_movement(&mut self) -> bool {
+        self.clear();
+        self.board.skip_movement()
+    }
+
+    /// Puts the piece on the tile, replacing whatever was there, for the board editor.
+    /// Return false if the position is not on the board.
+    pub fn place(&mut self, pos: Pos, piece: Piece) -> bool {
+        match self.board.get_mut(&pos) {
+            Some(tile) => {
+                tile.replace(piece);
+                self.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the piece of the tile, for the board editor.
+    /// Return false if the position is not on the board.
+    pub fn erase(&mut self, pos: Pos) -> bool {
+        self.place(pos, Piece::None)
+    }
+
+    /// A short text of the shape and the pieces of the board, to share custom positions.
+    /// The squares of the shape as `x.y.width.height`, then a `/` and the pieces as
+    /// `Name.color.x.y`, both separated by commas, e.g. `0.0.8.8/King.w.4.0,Rook.b.0.7`.
+    /// Only the placement is kept, not the time, the cards nor the effects of the pieces.
+    pub fn to_setup_code(&self) -> String {
+        let squares = self
+            .board
+            .shape()
+            .squares
+            .iter()
+            .map(|s| format!("{}.{}.{}.{}", s.anchor.x, s.anchor.y, s.width, s.height))
+            .collect::<Vec<_>>()
+            .join(",");
+        let pieces = self
+            .board
+            .tiles
+            .iter()
+            .filter_map(|tile| {
+                let color = match tile.piece.color()? {
+                    Color::White => 'w',
+                    Color::Black => 'b',
+                };
+                let pos = tile.pos();
+                Some(format!(
+                    "{}.{}.{}.{}",
+                    tile.piece.name(),
+                    color,
+                    pos.x,
+                    pos.y
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{squares}/{pieces}")
+    }
+
+    /// Parses a text made by `to_setup_code`, return None if it is malformed, places
+    /// a piece outside of the shape or the shape is bigger than `MAX_SETUP_SIDE` and
+    /// `MAX_SETUP_TILES` allow.
+    pub fn from_setup_code(code: &str) -> Option<Self> {
+        let (squares, pieces) = code.trim().split_once('/')?;
+        let squares = squares
+            .split(',')
+            .map(|square| {
+                let parts = square
+                    .split('.')
+                    .map(|n| n.parse().ok())
+                    .collect::<Option<Vec<usize>>>()?;
+                match parts[..] {
+                    [x, y, width, height] => Some(Square {
+                        anchor: Pos::new(x, y),
+                        width,
+                        height,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut tiles = 0usize;
+        for square in &squares {
+            let right = square.anchor.x.checked_add(square.width)?;
+            let top = square.anchor.y.checked_add(square.height)?;
+            tiles = tiles.checked_add(square.width.checked_mul(square.height)?)?;
+            if right > MAX_SETUP_SIDE || top > MAX_SETUP_SIDE || tiles > MAX_SETUP_TILES {
+                return None;
+            }
+        }
+        let mut chess = Self {
+            board: Board::with_shape(Shape::new(squares)),
+            ..Default::default()
+        };
+        for piece in pieces.split(',').filter(|piece| !piece.is_empty()) {
+            let parts = piece.split('.').collect::<Vec<_>>();
+            let [name, color, x, y] = parts[..] else {
+                return None;
+            };
+            let color = match color {
+                "w" => Color::White,
+                "b" => Color::Black,
+                _ => return None,
+            };
+            let pos = Pos::new(x.parse().ok()?, y.parse().ok()?);
+            if !chess.place(pos, Piece::from_name(name, color)?) {
+                return None;
+            }
+        }
+        Some(chess)
+    }
+
+    /// Whether the piece at `pos` belongs to the current player.
+    fn is_owned(&self, pos: &Pos) -> bool {
+        self.board
+            .get_piece(pos)
+            .and_then(Piece::color)
+            .is_some_and(|color| color == self.board.current_player().color())
+    }
+
     fn clear(&mut self) {
         self.moves.clear();
         self.takes.clear();
@@ -96,6 +210,24 @@ impl CChess {
         self.selected = None;
     }
 
+    /// Every ability the piece at `pos` can use right now, trying each direction and each
+    /// position of the board as its info, so clients can list them without the engine.
+    pub fn ability_targets(&self, pos: &Pos) -> Vec<Action> {
+        let Some(piece) = self.board.get_piece(pos) else {
+            return Vec::new();
+        };
+        if !self.board.can_afford_ability(pos) {
+            return Vec::new();
+        }
+        Direction::ALL
+            .into_iter()
+            .map(Info::Direction)
+            .chain(self.board.shape().points_iter().map(Info::Pos))
+            .map(|info| Action::ability(pos, info))
+            .filter(|action| piece.can_do(&self.board, action.clone()))
+            .collect()
+    }
+
     pub fn actions_at(&self, pos: &Pos) -> Vec<Action> {
         let mut actions = Vec::with_capacity(4);
         match (self.board.get(pos), &self.selected) {
@@ -109,13 +241,28 @@ impl CChess {
                 if self.attacks.contains(tile.pos()) {
                     actions.push(Action::attack(selected, tile.pos()));
                 }
+                if self.abilities.contains(tile.pos()) {
+                    actions.extend(Action::ability_from_click(
+                        &self.board,
+                        selected,
+                        tile.pos(),
+                    ));
+                }
                 actions
-                // TODO: handle ability
             }
             _ => actions,
         }
     }
 
+    /// Whether clicking `pos` would make one of the selected piece's actions, or select a piece of
+    /// the current player when nothing is selected.
+    pub fn is_actionable(&self, pos: &Pos) -> bool {
+        match &self.selected {
+            Some(_) => !self.actions_at(pos).is_empty(),
+            None => self.is_owned(pos),
+        }
+    }
+
     pub fn default_display() -> Self {
         let mut board = Board::with_shape(Shape::new(vec![Square {
             anchor: Pos::new(0, 0),
@@ -352,10 +499,22 @@ impl CChess {
         self.board.height()
     }
 
+    pub fn width(&self) -> usize {
+        self.board.width()
+    }
+
     pub fn row_iter(&self, row: usize) -> impl Iterator<Item = &Tile> {
         self.board.row_iter(row)
     }
 
+    pub fn display_rows(&self, flipped: bool) -> Vec<usize> {
+        self.board.display_rows(flipped)
+    }
+
+    pub fn display_row(&self, row: usize, flipped: bool) -> Vec<&Tile> {
+        self.board.display_row(row, flipped)
+    }
+
     pub fn has_move(&self, pos: &Pos) -> bool {
         self.moves.contains(pos)
     }
@@ -367,4 +526,198 @@ impl CChess {
     pub fn has_attack(&self, pos: &Pos) -> bool {
         self.attacks.contains(pos)
     }
+
+    pub fn has_ability(&self, pos: &Pos) -> bool {
+        self.abilities.contains(pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn actions_from_clicks() {
+        let chess = CChess::default_chessboard();
+        let board = &chess.board;
+        assert!(matches!(
+            Action::from_click(board, &Pos::new(1, 0), &Pos::new(2, 2)),
+            Some(Action::Move { .. })
+        ));
+        assert!(Action::from_click(board, &Pos::new(1, 0), &Pos::new(1, 2)).is_none());
+        assert!(Action::from_click(board, &Pos::new(3, 3), &Pos::new(3, 4)).is_none());
+
+        let mut board = board.clone();
+        board
+            .get_mut(&Pos::new(2, 2))
+            .unwrap()
+            .replace(Piece::pawn(Color::Black));
+        assert!(matches!(
+            Action::from_click(&board, &Pos::new(1, 0), &Pos::new(2, 2)),
+            Some(Action::Take { .. })
+        ));
+    }
+
+    #[test]
+    fn bishop_shifts_through_clicks() {
+        let mut chess = CChess::default();
+        let bishop = Pos::new(3, 3);
+        chess.place(bishop.clone(), Piece::bishop(Color::White));
+        chess.place(Pos::new(4, 3), Piece::pawn(Color::White));
+
+        chess.click(bishop.clone());
+        let mut abilities = chess.abilities.clone();
+        abilities.sort();
+        assert_eq!(
+            abilities,
+            vec![Pos::new(2, 3), Pos::new(3, 2), Pos::new(3, 4)]
+        );
+        assert!(chess.has_ability(&Pos::new(3, 4)));
+        assert!(matches!(
+            chess.actions_at(&Pos::new(3, 4))[..],
+            [Action::Ability {
+                info: Info::Direction(Direction::N),
+                ..
+            }]
+        ));
+
+        chess.click(Pos::new(3, 4));
+        assert!(chess.board.is_empty(&bishop));
+        assert!(matches!(
+            chess.board.get_piece(&Pos::new(3, 4)),
+            Some(Piece::Bishop(_))
+        ));
+        assert!(chess.abilities.is_empty());
+    }
+
+    #[test]
+    fn only_the_current_player_selects() {
+        let mut chess = CChess::default_chessboard();
+        let black_knight = Pos::new(1, 7);
+        assert!(!chess.click(black_knight.clone()));
+        assert_eq!(chess.selected, None);
+        assert!(chess.moves.is_empty());
+        assert!(!chess.click(Pos::new(4, 4)));
+        assert_eq!(chess.selected, None);
+
+        chess.click(Pos::new(1, 0));
+        chess.click(Pos::new(2, 2));
+        assert!(chess.click(black_knight.clone()));
+        assert_eq!(chess.selected, Some(black_knight));
+        assert!(!chess.moves.is_empty());
+    }
+
+    #[test]
+    fn bishop_ability_targets() {
+        let mut chess = CChess::default();
+        let bishop = Pos::new(3, 3);
+        chess.place(bishop.clone(), Piece::bishop(Color::White));
+        let targets = chess.ability_targets(&bishop);
+        assert_eq!(targets.len(), 4);
+        assert!(targets.iter().all(|action| matches!(
+            action,
+            Action::Ability {
+                info: Info::Direction(_),
+                ..
+            }
+        )));
+        assert!(chess.ability_targets(&Pos::new(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn taking_the_king_ends_the_game() {
+        let mut chess = CChess::default();
+        let (rook, black_king, white_king) = (Pos::new(0, 0), Pos::new(0, 5), Pos::new(7, 0));
+        chess.place(rook.clone(), Piece::rook(Color::White));
+        chess.place(black_king.clone(), Piece::king(Color::Black));
+        chess.place(white_king.clone(), Piece::king(Color::White));
+        assert_eq!(chess.board.winner(), None);
+
+        chess.click(rook);
+        chess.click(black_king.clone());
+        assert!(matches!(
+            chess.board.get_piece(&black_king),
+            Some(Piece::Rook(_))
+        ));
+        assert_eq!(chess.board.winner(), Some(Color::White));
+        // black is to move, but the game is over
+        let black_pawn = Pos::new(5, 5);
+        chess.place(black_pawn.clone(), Piece::pawn(Color::Black));
+        assert!(!chess.click(black_pawn));
+        assert_eq!(chess.selected, None);
+    }
+
+    #[test]
+    fn place_and_erase() {
+        let mut chess = CChess::default_chessboard();
+        let pos = Pos::new(3, 3);
+        assert!(chess.place(pos.clone(), Piece::queen(Color::Black)));
+        assert_eq!(
+            chess.board.get_piece(&pos),
+            Some(&Piece::queen(Color::Black))
+        );
+        assert!(chess.erase(pos.clone()));
+        assert!(chess.board.is_empty(&pos));
+
+        chess.click(Pos::new(1, 0));
+        assert!(chess.erase(Pos::new(1, 0)));
+        assert_eq!(chess.selected, None);
+        assert!(chess.moves.is_empty());
+        assert!(!chess.place(Pos::new(100, 100), Piece::pawn(Color::White)));
+        assert!(!chess.erase(Pos::new(8, 0)));
+    }
+
+    #[test]
+    fn setup_code_round_trip() {
+        let mut chess = CChess {
+            board: Board::with_shape(Shape::cross_shape()),
+            ..Default::default()
+        };
+        chess.place(Pos::new(3, 0), Piece::king(Color::White));
+        chess.place(Pos::new(4, 7), Piece::king(Color::Black));
+        chess.place(Pos::new(0, 2), Piece::magician(Color::White));
+        chess.place(Pos::new(7, 5), Piece::tesla_tower(Color::Black));
+
+        let code = chess.to_setup_code();
+        let parsed = CChess::from_setup_code(&code).unwrap();
+        assert_eq!(parsed.board.shape(), chess.board.shape());
+        assert_eq!(parsed.board.tiles, chess.board.tiles);
+        assert_eq!(parsed.to_setup_code(), code);
+
+        assert!(CChess::from_setup_code("0.0.8.8/")
+            .unwrap()
+            .board
+            .is_empty(&Pos::new(0, 0)));
+        assert!(CChess::from_setup_code("0.0.8.8/King.w.9.0").is_none());
+        assert!(CChess::from_setup_code("0.0.8.8/Dragon.w.1.0").is_none());
+        assert!(CChess::from_setup_code("0.0.8/King.w.1.0").is_none());
+    }
+
+    #[test]
+    fn setup_code_size_is_capped() {
+        assert!(CChess::from_setup_code(&CChess::cchessboard().to_setup_code()).is_some());
+        assert!(CChess::from_setup_code("0.0.32.32/").is_some());
+        assert!(CChess::from_setup_code("0.0.64.16/").is_some());
+
+        assert!(CChess::from_setup_code("0.0.65.1/").is_none());
+        assert!(CChess::from_setup_code("60.0.8.8/").is_none());
+        assert!(CChess::from_setup_code("0.0.64.64/").is_none());
+        assert!(CChess::from_setup_code("0.0.32.32,0.0.1.1/").is_none());
+        let huge = format!("0.0.{}.{}/", usize::MAX, usize::MAX);
+        assert!(CChess::from_setup_code(&huge).is_none());
+    }
+
+    #[test]
+    fn actionable_tiles() {
+        let mut chess = CChess::default_chessboard();
+        assert!(!chess.is_actionable(&Pos::new(3, 3)));
+        assert!(!chess.is_actionable(&Pos::new(1, 7)));
+        assert!(!chess.is_actionable(&Pos::new(100, 100)));
+        assert!(chess.is_actionable(&Pos::new(1, 0)));
+
+        chess.click(Pos::new(1, 0));
+        assert!(chess.is_actionable(&Pos::new(2, 2)));
+        assert!(!chess.is_actionable(&Pos::new(1, 2)));
+        assert!(!chess.is_actionable(&Pos::new(1, 0)));
+    }
 }
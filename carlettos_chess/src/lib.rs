@@ -1,15 +1,21 @@
-use std::ops::{Add, AddAssign, Sub};
+use std::{
+    fmt, iter,
+    ops::{Add, AddAssign, Sub},
+};
 
+use board::Board;
 use piece::Piece;
 use serde::{Deserialize, Serialize};
 
 pub mod ability;
+pub mod ai;
 pub mod board;
 pub mod card;
 pub mod chess_controller;
 pub mod pattern;
 pub mod piece;
 pub mod prelude;
+pub mod replay;
 
 /// Represents a position on a chessboard.
 ///
@@ -37,7 +43,7 @@ pub mod prelude;
 /// let west_pos = pos.west();
 /// assert_eq!(west_pos.unwrap().x, 2);
 /// ```
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Pos {
     pub x: usize,
     pub y: usize,
@@ -306,6 +312,8 @@ impl From<&Direction> for (isize, isize) {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::N, Direction::E, Direction::S, Direction::W];
+
     pub fn is_axis(&self, axis: &Axis) -> bool {
         matches!(
             (self, axis),
@@ -398,7 +406,10 @@ pub enum Axis {
     EW,
 }
 
+/// Serialized with a "type" field naming the variant next to its fields,
+/// e.g. `{"type":"Move","from":{"x":0,"y":1},"to":{"x":0,"y":2}}`.
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type")]
 pub enum Action {
     /// Indicates a moving piece
     Move { from: Pos, to: Pos },
@@ -439,6 +450,43 @@ impl Action {
         }
     }
 
+    /// The action of the piece at `selected` on `target`, preferring an attack, then a take,
+    /// then a move. None if the piece can't do anything there.
+    pub fn from_click(board: &Board, selected: &Pos, target: &Pos) -> Option<Action> {
+        let actions = board
+            .actions_from(selected)
+            .into_iter()
+            .filter(|action| match action {
+                Action::Move { to, .. } | Action::Take { to, .. } | Action::Attack { to, .. } => {
+                    to == target
+                }
+                Action::Ability { .. } => false,
+            })
+            .collect::<Vec<_>>();
+        let find = |is: fn(&Action) -> bool| actions.iter().find(|action| is(action));
+        find(Action::is_attack)
+            .or_else(|| find(Action::is_take))
+            .or_else(|| find(Action::is_move))
+            .cloned()
+    }
+
+    /// The ability of the piece at `selected` aimed at `target` by a click, as the
+    /// direction from `selected` when `target` is its neighbor, or else as the position.
+    /// Abilities that need other infos can't be chosen by a click.
+    pub fn ability_from_click(board: &Board, selected: &Pos, target: &Pos) -> Option<Action> {
+        let piece = board.get_piece(selected)?;
+        if !board.can_afford_ability(selected) {
+            return None;
+        }
+        Direction::ALL
+            .into_iter()
+            .filter(|direction| selected.direction_shift(direction).as_ref() == Some(target))
+            .map(Info::Direction)
+            .chain(iter::once(Info::Pos(target.clone())))
+            .map(|info| Action::ability(selected, info))
+            .find(|action| piece.can_do(board, action.clone()))
+    }
+
     pub fn is_move(&self) -> bool {
         matches!(self, Self::Move { from: _, to: _ })
     }
@@ -456,7 +504,7 @@ impl Action {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Default, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 pub struct Time {
     pub round: usize,
     pub turn: usize,
@@ -497,15 +545,15 @@ impl Time {
     }
 
     pub fn on_round(&mut self) {
-        self.round = self.round.checked_sub(1).unwrap_or_default();
+        self.round = self.round.saturating_sub(1);
     }
 
     pub fn on_turn(&mut self) {
-        self.turn = self.turn.checked_sub(1).unwrap_or_default();
+        self.turn = self.turn.saturating_sub(1);
     }
 
     pub fn on_movement(&mut self) {
-        self.movement = self.movement.checked_sub(1).unwrap_or_default();
+        self.movement = self.movement.saturating_sub(1);
     }
 
     pub fn is_zero(&self) -> bool {
@@ -523,6 +571,45 @@ impl Time {
     pub fn is_movement(&self) -> bool {
         self.movement != 0 && self.turn == 0 && self.round == 0
     }
+
+    /// The non zero parts, from rounds to movements.
+    fn parts(&self) -> impl Iterator<Item = (usize, &'static str)> {
+        [
+            (self.round, "round"),
+            (self.turn, "turn"),
+            (self.movement, "movement"),
+        ]
+        .into_iter()
+        .filter(|(amount, _)| *amount != 0)
+    }
+
+    /// Compact form for small spaces like the HUD, e.g. "10r" or "2t 1m". Zero is "0".
+    pub fn describe(&self) -> String {
+        if self.is_zero() {
+            return "0".to_string();
+        }
+        self.parts()
+            .map(|(amount, unit)| format!("{amount}{}", &unit[..1]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Long form for tooltips, e.g. "10 rounds" or "2 turns, 1 movement".
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "no time");
+        }
+        let parts = self
+            .parts()
+            .map(|(amount, unit)| match amount {
+                1 => format!("1 {unit}"),
+                _ => format!("{amount} {unit}s"),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", parts.join(", "))
+    }
 }
 
 impl Sub for Time {
@@ -597,14 +684,26 @@ impl Add for &Time {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Color {
     #[default]
     White,
     Black,
 }
 
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+/// Serialized with a "type" field naming the variant and a "value" field with its content,
+/// e.g. `{"type":"Integer","value":2}`.
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type", content = "value")]
 pub enum Info {
     Piece(Piece),
     Direction(Direction),
@@ -621,11 +720,71 @@ pub enum PaladinAbilityType {
     Revive(Pos),
     Invulnerability(Pos),
     Attack(Pos),
+    Dispel(Pos),
 }
 
 #[cfg(test)]
 mod test {
-    use crate::Pos;
+    use crate::{Action, Direction, Info, Pos, SubDirection, Time};
+
+    #[test]
+    fn action_json() {
+        let (from, to) = (Pos::new(0, 1), Pos::new(2, 3));
+        let json = |action: &Action| serde_json::to_string(action).unwrap();
+        assert_eq!(
+            json(&Action::r#move(&from, &to)),
+            r#"{"type":"Move","from":{"x":0,"y":1},"to":{"x":2,"y":3}}"#
+        );
+        assert_eq!(
+            json(&Action::take(&from, &to)),
+            r#"{"type":"Take","from":{"x":0,"y":1},"to":{"x":2,"y":3}}"#
+        );
+        assert_eq!(
+            json(&Action::attack(&from, &to)),
+            r#"{"type":"Attack","from":{"x":0,"y":1},"to":{"x":2,"y":3}}"#
+        );
+        assert_eq!(
+            json(&Action::ability(&from, Info::Integer(2))),
+            r#"{"type":"Ability","from":{"x":0,"y":1},"info":{"type":"Integer","value":2}}"#
+        );
+    }
+
+    #[test]
+    fn ability_round_trip() {
+        let info = Info::Trio(
+            Box::new(Info::Direction(Direction::N)),
+            Box::new(Info::SubDirection(SubDirection::E)),
+            Box::new(Info::Integer(2)),
+        );
+        let action = Action::ability(&Pos::new(3, 3), info);
+        let json = serde_json::to_string(&action).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"Ability","from":{"x":3,"y":3},"info":{"type":"Trio","value":[{"type":"Direction","value":"N"},{"type":"SubDirection","value":"E"},{"type":"Integer","value":2}]}}"#
+        );
+        let back: Action = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        assert!(matches!(
+            back,
+            Action::Ability {
+                info: Info::Trio(..),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn time_descriptions() {
+        assert_eq!(Time::rounds(10).describe(), "10r");
+        assert_eq!(Time::rounds(10).to_string(), "10 rounds");
+        assert_eq!(Time::turns(1).describe(), "1t");
+        assert_eq!(Time::turns(1).to_string(), "1 turn");
+        assert_eq!(Time::new(0, 2, 1).describe(), "2t 1m");
+        assert_eq!(Time::new(0, 2, 1).to_string(), "2 turns, 1 movement");
+        assert_eq!(Time::new(3, 0, 2).describe(), "3r 2m");
+        assert_eq!(Time::default().describe(), "0");
+        assert_eq!(Time::default().to_string(), "no time");
+    }
 
     #[test]
     fn pos_add() {
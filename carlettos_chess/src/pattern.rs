@@ -39,8 +39,8 @@ pub fn bishop(board: &Board, from: &Pos, to: &Pos) -> bool {
     let signx = if to.x > from.x { 1isize } else { -1 };
     let signy = if to.y > from.y { 1isize } else { -1 };
     board
-        .ray_cast_empty(from, None, &(signx, signy))
-        .contains(to)
+        .ray_iter(from, (signx, signy))
+        .any(|tile| tile.pos() == to)
 }
 
 pub fn rook(board: &Board, from: &Pos, to: &Pos) -> bool {
@@ -52,8 +52,8 @@ pub fn rook(board: &Board, from: &Pos, to: &Pos) -> bool {
     let signx = to.x.cmp(&from.x) as isize;
     let signy = to.y.cmp(&from.y) as isize;
     board
-        .ray_cast_empty(from, None, &(signx, signy))
-        .contains(to)
+        .ray_iter(from, (signx, signy))
+        .any(|tile| tile.pos() == to)
 }
 
 pub fn queen(board: &Board, from: &Pos, to: &Pos) -> bool {
@@ -157,6 +157,45 @@ mod test {
     use crate::Color;
     use crate::Pos;
 
+    #[test]
+    fn every_piece_stays_on_board_and_sliders_move_like_they_take() {
+        // these pieces take the same way they move
+        let symmetric = [
+            "Knight",
+            "Bishop",
+            "Rook",
+            "Queen",
+            "King",
+            "CrazyPawn",
+            "Paladin",
+            "TeslaTower",
+        ];
+        let mut kinds = Vec::new();
+        for new_piece in Piece::ALL {
+            for color in [Color::White, Color::Black] {
+                let piece = new_piece(color.clone());
+                assert_eq!(piece.color(), Some(&color));
+                kinds.push(piece.name());
+                for from in [Pos::new(0, 0), Pos::new(3, 4), Pos::new(7, 7)] {
+                    let mut board = Board::default();
+                    board.get_mut(&from).unwrap().replace(piece.clone());
+                    for to in (0..12).flat_map(|x| (0..12).map(move |y| Pos::new(x, y))) {
+                        let can_move = piece.can_do(&board, Action::r#move(&from, &to));
+                        let can_take = piece.can_do(&board, Action::take(&from, &to));
+                        let can_attack = piece.can_do(&board, Action::attack(&from, &to));
+                        if !board.contains(&to) {
+                            assert!(!can_move && !can_take && !can_attack, "{:?}", piece);
+                        } else if symmetric.contains(&piece.name()) {
+                            assert_eq!(can_move, can_take, "{:?} {:?}", piece, to);
+                        }
+                    }
+                }
+            }
+        }
+        kinds.dedup();
+        assert_eq!(kinds.len(), Piece::ALL.len());
+    }
+
     fn pawn_move(color: Color) {
         let sign = match color {
             Color::Black => -1,
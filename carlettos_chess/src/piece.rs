@@ -1,13 +1,15 @@
+use std::mem;
+
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ability::{self, Ability},
-    board::Board,
+    ability::{self, Ability, AbilityInfo},
+    board::{Board, DEMONIC_MANA},
     pattern::{self},
-    Action, Color, Info, Pos, Time,
+    Action, Color, Direction, Info, PaladinAbilityType, Pos, Time,
 };
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub struct PieceData {
     pub moved: bool,
     pub cooldown: Time,
@@ -39,7 +41,10 @@ impl PieceData {
         self.types.can_do(action) && self.effects.can_do(action)
     }
 
-    pub fn on_do(&self, action: &Action) {
+    /// Called by the board on the piece that made the action, marking it as moved for
+    /// the rest of the turn.
+    pub fn on_do(&mut self, action: &Action) {
+        self.moved = true;
         self.types.on_do(action);
         self.effects.on_do(action);
     }
@@ -48,9 +53,10 @@ impl PieceData {
         self.types.can_be(action) && self.effects.can_be(action)
     }
 
-    pub fn on_be(&self, action: &Action) {
-        self.types.on_be(action);
-        self.effects.on_be(action);
+    /// Called by the board on the victim of a take or an attack, once it is off the board.
+    pub fn on_be(&self, board: &mut Board, action: &Action) {
+        self.types.on_be(board, &self.color, action);
+        self.effects.on_be(board, action);
     }
 
     pub fn get_strength(&self) -> usize {
@@ -58,13 +64,23 @@ impl PieceData {
     }
 
     pub fn add_effect(&mut self, effect: Effect) {
-        self.effects.0.push(effect)
+        self.effects.add(effect)
     }
 
     pub fn has_effect(&self, effect: &Effect) -> bool {
         self.effects.0.contains(effect)
     }
 
+    /// Removes the harmful effects, see `Effect::is_negative`.
+    pub fn dispel(&mut self) {
+        self.effects.0.retain(|e| !e.is_negative())
+    }
+
+    /// Whether the piece has an effect of this kind, whatever the time left.
+    pub fn has_effect_kind(&self, kind: mem::Discriminant<Effect>) -> bool {
+        self.effects.0.iter().any(|e| mem::discriminant(e) == kind)
+    }
+
     /// Tick this PieceData, the time param should be ONLY a round, turn or movement. This method doesn't handle mixes of times.
     pub fn tick(&mut self, time: &Time) {
         self.effects.pre_tick(time);
@@ -80,7 +96,7 @@ impl PieceData {
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub enum Piece {
     #[default]
     None,
@@ -116,6 +132,68 @@ pub enum Piece {
 }
 
 impl Piece {
+    /// The constructor of every kind of piece.
+    pub const ALL: [fn(Color) -> Piece; 22] = [
+        Piece::pawn,
+        Piece::knight,
+        Piece::bishop,
+        Piece::rook,
+        Piece::queen,
+        Piece::king,
+        Piece::archer,
+        Piece::ballista,
+        Piece::builder,
+        Piece::cannon,
+        Piece::catapult,
+        Piece::crazy_pawn,
+        Piece::magician,
+        Piece::paladin,
+        Piece::ram,
+        Piece::shield_bearer,
+        Piece::ship,
+        Piece::super_pawn,
+        Piece::tesla_tower,
+        Piece::wall,
+        Piece::warlock,
+        Piece::portal,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Piece::None => "None",
+            Piece::Pawn(_) => "Pawn",
+            Piece::Knight(_) => "Knight",
+            Piece::Bishop(_) => "Bishop",
+            Piece::Rook(_) => "Rook",
+            Piece::Queen(_) => "Queen",
+            Piece::King(_) => "King",
+            Piece::Archer(_) => "Archer",
+            Piece::Ballista(_) => "Ballista",
+            Piece::Builder(_) => "Builder",
+            Piece::Cannon(_) => "Cannon",
+            Piece::Catapult(_) => "Catapult",
+            Piece::CrazyPawn(_) => "CrazyPawn",
+            Piece::Magician(_) => "Magician",
+            Piece::Paladin(_) => "Paladin",
+            Piece::Ram(_) => "Ram",
+            Piece::ShieldBearer(_) => "ShieldBearer",
+            Piece::Ship(_) => "Ship",
+            Piece::SuperPawn(_) => "SuperPawn",
+            Piece::TeslaTower(_) => "TeslaTower",
+            Piece::Wall(_) => "Wall",
+            Piece::Warlock(_) => "Warlock",
+            Piece::Portal(_) => "Portal",
+        }
+    }
+
+    /// The piece with this `name` and color, the inverse of `Piece::name`.
+    pub fn from_name(name: &str, color: Color) -> Option<Piece> {
+        Piece::ALL
+            .iter()
+            .map(|piece| piece(color.clone()))
+            .find(|piece| piece.name() == name)
+    }
+
     pub fn color(&self) -> Option<&Color> {
         match self {
             Piece::None => None,
@@ -200,6 +278,15 @@ impl Piece {
         }
     }
 
+    /// A copy of the piece with the given color, `Piece::None` stays the same.
+    pub fn with_color(&self, color: Color) -> Piece {
+        let mut piece = self.clone();
+        if let Some(data) = piece.mut_data() {
+            data.color = color;
+        }
+        piece
+    }
+
     pub fn tick(&mut self, time: &Time) {
         if let Some(data) = self.mut_data() {
             data.tick(time);
@@ -241,13 +328,23 @@ impl Piece {
     }
 
     pub fn is_transportable(&self, max_weight: &usize) -> bool {
-        match self.data() {
-            None => false,
-            Some(data) => data
-                .types
-                .0
-                .iter()
-                .any(|t| matches!(t, Type::Transportable(weight) if weight <= max_weight)),
+        self.weight().is_some_and(|weight| &weight <= max_weight)
+    }
+
+    /// The weight of the piece, if it can be transported.
+    pub fn weight(&self) -> Option<usize> {
+        self.data()?.types.0.iter().find_map(|t| match t {
+            Type::Transportable(weight) => Some(*weight),
+            _ => None,
+        })
+    }
+
+    /// The heaviest transportable piece this piece can carry, if it can carry any.
+    pub fn carry_capacity(&self) -> Option<usize> {
+        match self {
+            Piece::Catapult(_) => Some(5),
+            Piece::Ship(_) => Some(4),
+            _ => None,
         }
     }
 
@@ -303,10 +400,17 @@ impl Piece {
     }
 
     pub fn can_do(&self, board: &Board, action: Action) -> bool {
+        let on_board = match &action {
+            Action::Move { to, .. } | Action::Take { to, .. } | Action::Attack { to, .. } => {
+                board.contains(to)
+            }
+            Action::Ability { .. } => true,
+        };
         match self.data() {
             None => false,
             Some(data) => {
-                data.can_do(&action)
+                on_board
+                    && data.can_do(&action)
                     && match (self, action) {
                         (Piece::None, _) => false,
                         (Piece::Pawn(data), Action::Move { from, to }) => {
@@ -370,7 +474,7 @@ impl Piece {
                         }
                         (Piece::Archer(_), Action::Take { from: _, to: _ }) => false,
                         (Piece::Archer(_), Action::Attack { from, to }) => {
-                            pattern::square(&from, &to, 4)
+                            from != to && pattern::square(&from, &to, 4)
                         }
                         (Piece::Archer(_), Action::Ability { from: _, info: _ }) => false,
                         (Piece::Ballista(_), Action::Move { from, to }) => {
@@ -403,7 +507,7 @@ impl Piece {
                         }
                         (Piece::Cannon(_), Action::Take { from: _, to: _ }) => false,
                         (Piece::Cannon(_), Action::Attack { from, to }) => {
-                            pattern::square(&from, &to, 3)
+                            from != to && pattern::square(&from, &to, 3)
                         }
                         (Piece::Cannon(_), Action::Ability { from: _, info: _ }) => false,
                         (Piece::Catapult(_), Action::Move { from, to }) => {
@@ -468,10 +572,10 @@ impl Piece {
                         (Piece::Ship(_), Action::Ability { from, info }) => {
                             ability::Ship::can_use(board, &from, &info)
                         }
-                        (Piece::SuperPawn(_), Action::Move { from, to }) => {
+                        (Piece::SuperPawn(data), Action::Move { from, to }) => {
                             pattern::super_pawn_move(board, &data.color, &from, &to)
                         }
-                        (Piece::SuperPawn(_), Action::Take { from, to }) => {
+                        (Piece::SuperPawn(data), Action::Take { from, to }) => {
                             pattern::super_pawn_take(board, &data.color, &from, &to)
                         }
                         (Piece::SuperPawn(_), Action::Attack { from: _, to: _ }) => false,
@@ -508,86 +612,98 @@ impl Piece {
         }
     }
 
+    /// Name, cost, cooldown and effect of the piece's ability, None if it has no ability.
+    pub fn ability_description(&self) -> Option<AbilityInfo> {
+        let data = match self {
+            Piece::None => return None,
+            Piece::Pawn(_) => ability::Pawn.data(),
+            Piece::Knight(_) => ability::Knight.data(),
+            Piece::Bishop(_) => ability::Bishop.data(),
+            Piece::Rook(_) => ability::Rook.data(),
+            Piece::Queen(_) => ability::Queen.data(),
+            Piece::King(_) => ability::King.data(),
+            Piece::Archer(_) => return None,
+            Piece::Ballista(_) => return None,
+            Piece::Builder(_) => ability::Builder.data(),
+            Piece::Cannon(_) => return None,
+            Piece::Catapult(_) => ability::Catapult.data(),
+            Piece::CrazyPawn(_) => ability::CrazyPawn.data(),
+            Piece::Magician(_) => ability::Magician.data(),
+            Piece::Paladin(_) => ability::Paladin.data(),
+            Piece::Ram(_) => ability::Ram.data(),
+            Piece::ShieldBearer(_) => ability::ShieldBearer.data(),
+            Piece::Ship(_) => ability::Ship.data(),
+            Piece::SuperPawn(_) => ability::SuperPawn.data(),
+            Piece::TeslaTower(_) => ability::TeslaTower.data(),
+            Piece::Wall(_) => return None,
+            Piece::Warlock(_) => ability::Warlock.data(),
+            Piece::Portal(_) => ability::Portal.data(),
+        };
+        Some(AbilityInfo {
+            name: self.name(),
+            cost: data.cost,
+            cooldown: data.cooldown,
+            description: data.description,
+        })
+    }
+
+    /// The tiles the ability would touch, so the UI can highlight them before it's used.
+    pub fn ability_area(&self, board: &Board, from: &Pos, info: &Info) -> Vec<Pos> {
+        match (self, info) {
+            (Piece::Magician(_), _) => {
+                board.pos_vec_from_pattern(from, |from, to| pattern::square(from, to, 4))
+            }
+            (Piece::TeslaTower(_), _) => {
+                board.pos_vec_from_pattern(from, |from, to| pattern::square(from, to, 3))
+            }
+            (Piece::ShieldBearer(_) | Piece::Warlock(_), _) => {
+                board.pos_vec_from_pattern(from, pattern::king)
+            }
+            (Piece::Ship(_), _) => [Direction::E, Direction::W]
+                .iter()
+                .flat_map(Direction::related_subdirections)
+                .filter_map(|subdir| from.subdirection_shift(&subdir))
+                .filter(|pos| board.contains(pos))
+                .collect(),
+            (Piece::Paladin(_), Info::PaladinAbilityInfo(ability_type)) => match ability_type {
+                PaladinAbilityType::Revive(to)
+                | PaladinAbilityType::Invulnerability(to)
+                | PaladinAbilityType::Attack(to)
+                | PaladinAbilityType::Dispel(to) => vec![to.clone()],
+            },
+            (_, Info::Pos(to)) => vec![to.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Uses the ability of the piece at `from`, `Board::make` calls `on_do` before it, as the
+    /// ability may move the piece away from its tile.
     pub fn ability(board: &mut Board, from: Pos, info: Info) {
         let piece = board.get(&from).unwrap().piece.clone();
         match piece {
             Piece::None => (),
-            Piece::Pawn(data) => {
-                ability::Pawn::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Knight(data) => {
-                ability::Knight::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Bishop(data) => {
-                ability::Bishop::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Rook(data) => {
-                ability::Rook::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Queen(data) => {
-                ability::Queen::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::King(data) => {
-                ability::King::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
+            Piece::Pawn(_) => ability::Pawn::r#use(board, &from, info),
+            Piece::Knight(_) => ability::Knight::r#use(board, &from, info),
+            Piece::Bishop(_) => ability::Bishop::r#use(board, &from, info),
+            Piece::Rook(_) => ability::Rook::r#use(board, &from, info),
+            Piece::Queen(_) => ability::Queen::r#use(board, &from, info),
+            Piece::King(_) => ability::King::r#use(board, &from, info),
             Piece::Archer(_) => (),
             Piece::Ballista(_) => (),
-            Piece::Builder(data) => {
-                ability::Builder::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
+            Piece::Builder(_) => ability::Builder::r#use(board, &from, info),
             Piece::Cannon(_) => (),
-            Piece::Catapult(data) => {
-                ability::Catapult::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::CrazyPawn(data) => {
-                ability::CrazyPawn::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Magician(data) => {
-                ability::Magician::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Paladin(data) => {
-                ability::Paladin::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Ram(data) => {
-                ability::Ram::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::ShieldBearer(data) => {
-                ability::ShieldBearer::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Ship(data) => {
-                ability::Ship::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::SuperPawn(data) => {
-                ability::SuperPawn::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::TeslaTower(data) => {
-                ability::TeslaTower::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
+            Piece::Catapult(_) => ability::Catapult::r#use(board, &from, info),
+            Piece::CrazyPawn(_) => ability::CrazyPawn::r#use(board, &from, info),
+            Piece::Magician(_) => ability::Magician::r#use(board, &from, info),
+            Piece::Paladin(_) => ability::Paladin::r#use(board, &from, info),
+            Piece::Ram(_) => ability::Ram::r#use(board, &from, info),
+            Piece::ShieldBearer(_) => ability::ShieldBearer::r#use(board, &from, info),
+            Piece::Ship(_) => ability::Ship::r#use(board, &from, info),
+            Piece::SuperPawn(_) => ability::SuperPawn::r#use(board, &from, info),
+            Piece::TeslaTower(_) => ability::TeslaTower::r#use(board, &from, info),
             Piece::Wall(_) => (),
-            Piece::Warlock(data) => {
-                ability::Warlock::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
-            Piece::Portal(data) => {
-                ability::Portal::r#use(board, &from, info.clone());
-                data.on_do(&Action::Ability { from, info });
-            }
+            Piece::Warlock(_) => ability::Warlock::r#use(board, &from, info),
+            Piece::Portal(_) => ability::Portal::r#use(board, &from, info),
         }
     }
 
@@ -742,7 +858,7 @@ impl Piece {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub enum Type {
     Biologic,
     Structure,
@@ -807,7 +923,8 @@ impl Type {
         }
     }
 
-    pub fn on_be(&self, _action: &Action) {
+    /// `color` is the color of the piece with this type.
+    pub fn on_be(&self, board: &mut Board, color: &Color, action: &Action) {
         match self {
             Type::Biologic => (),
             Type::Structure => (),
@@ -815,19 +932,28 @@ impl Type {
             Type::Impenetrable(_) => (),
             Type::Immune => (),
             Type::Heroic => (),
-            Type::Demonic => todo!("Add mana to player on dead"),
-            Type::Tough(_) => todo!("implement toughness"),
+            Type::Demonic if action.is_take() || action.is_attack() => {
+                if let Some(player) = board.mut_player_from_color(color) {
+                    player.mana += DEMONIC_MANA;
+                }
+            }
+            Type::Demonic => (),
+            // TODO: implement toughness
+            Type::Tough(_) => (),
             Type::Dead => (),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub enum Effect {
     Deactivate(Time),
     Fire(Time),
     Ice(Time),
     Invulnerability(Time),
+    /// Counts the calls to `on_do` of its piece, for the tests of `Board::make`.
+    #[cfg(test)]
+    Counter(Time, usize),
 }
 
 impl Effect {
@@ -853,15 +979,19 @@ impl Effect {
             Effect::Fire(_) => true,
             Effect::Ice(_) => false,
             Effect::Invulnerability(_) => true,
+            #[cfg(test)]
+            Effect::Counter(..) => true,
         }
     }
 
-    pub fn on_do(&self, _action: &Action) {
+    pub fn on_do(&mut self, _action: &Action) {
         match self {
             Effect::Deactivate(_) => (),
             Effect::Fire(_) => (),
             Effect::Ice(_) => (),
             Effect::Invulnerability(_) => (),
+            #[cfg(test)]
+            Effect::Counter(_, calls) => *calls += 1,
         }
     }
 
@@ -871,24 +1001,67 @@ impl Effect {
             Effect::Fire(_) => true,
             Effect::Ice(_) => true,
             Effect::Invulnerability(_) => false,
+            #[cfg(test)]
+            Effect::Counter(..) => true,
         }
     }
 
-    pub fn on_be(&self, _action: &Action) {
+    pub fn on_be(&self, _board: &mut Board, _action: &Action) {
         match self {
             Effect::Deactivate(_) => (),
             Effect::Fire(_) => (),
             Effect::Ice(_) => (),
             Effect::Invulnerability(_) => (),
+            #[cfg(test)]
+            Effect::Counter(..) => (),
         }
     }
 
+    /// The time left for this effect.
+    pub fn time(&self) -> &Time {
+        match self {
+            Effect::Deactivate(time) => time,
+            Effect::Fire(time) => time,
+            Effect::Ice(time) => time,
+            Effect::Invulnerability(time) => time,
+            #[cfg(test)]
+            Effect::Counter(time, _) => time,
+        }
+    }
+
+    /// Whether both effects are of the same kind, whatever the time left.
+    pub fn same_kind(&self, other: &Effect) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+
+    /// Whether this effect harms the piece, these are the ones removed by a dispel.
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Effect::Deactivate(_) => true,
+            Effect::Fire(_) => true,
+            Effect::Ice(_) => true,
+            Effect::Invulnerability(_) => false,
+            #[cfg(test)]
+            Effect::Counter(..) => false,
+        }
+    }
+
+    /// Whether this effect cancels the restrictions of `other` while both are active.
+    pub fn suppresses(&self, other: &Effect) -> bool {
+        matches!(
+            (self, other),
+            (Effect::Invulnerability(_), Effect::Deactivate(_))
+        )
+    }
+
     pub fn pre_tick(&mut self, time: &Time) {
         let cd = match self {
             Effect::Deactivate(time) => time,
             Effect::Fire(time) => time,
             Effect::Ice(time) => time,
             Effect::Invulnerability(time) => time,
+            #[cfg(test)]
+            Effect::Counter(time, _) => time,
         };
 
         if time.is_movement() {
@@ -900,19 +1073,20 @@ impl Effect {
         }
     }
 
-    pub fn post_tick(&mut self, time: &Time) {
+    pub fn post_tick(&mut self, _time: &Time) {
         match self {
             Effect::Deactivate(_) => (),
-            Effect::Fire(time) => {
-                // TODO: this should kill the piece
-            }
+            // the board kills the piece once the fire goes out, see `Board::tick`
+            Effect::Fire(_) => (),
             Effect::Ice(_) => (),
             Effect::Invulnerability(_) => (),
+            #[cfg(test)]
+            Effect::Counter(..) => (),
         }
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub enum Property {
     #[default]
     None,
@@ -926,7 +1100,7 @@ pub enum Property {
     Strength(usize),
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub struct Types(pub Vec<Type>);
 
 impl Types {
@@ -942,41 +1116,71 @@ impl Types {
         self.0.iter().all(|t| t.can_be(action))
     }
 
-    pub fn on_be(&self, action: &Action) {
-        self.0.iter().for_each(|t| t.on_do(action))
+    pub fn on_be(&self, board: &mut Board, color: &Color, action: &Action) {
+        self.0.iter().for_each(|t| t.on_be(board, color, action))
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub struct Effects(pub Vec<Effect>);
 
 impl Effects {
+    /// Adds the effect, an effect of the same kind is refreshed to the longer of both times instead.
+    pub fn add(&mut self, effect: Effect) {
+        match self.0.iter_mut().find(|e| e.same_kind(&effect)) {
+            Some(current) => {
+                if effect.time() > current.time() {
+                    *current = effect;
+                }
+            }
+            None => self.0.push(effect),
+        }
+    }
+
+    /// The effects that aren't suppressed by another one.
+    fn active(&self) -> impl Iterator<Item = &Effect> {
+        self.0
+            .iter()
+            .filter(|e| !self.0.iter().any(|other| other.suppresses(e)))
+    }
+
     pub fn can_do(&self, action: &Action) -> bool {
-        self.0.iter().all(|e| e.can_do(action))
+        self.active().all(|e| e.can_do(action))
     }
 
-    pub fn on_do(&self, action: &Action) {
-        self.0.iter().for_each(|e| e.on_do(action))
+    pub fn on_do(&mut self, action: &Action) {
+        let active: Vec<bool> = self
+            .0
+            .iter()
+            .map(|e| !self.0.iter().any(|other| other.suppresses(e)))
+            .collect();
+        self.0
+            .iter_mut()
+            .zip(active)
+            .filter(|(_, active)| *active)
+            .for_each(|(e, _)| e.on_do(action))
     }
 
     pub fn can_be(&self, action: &Action) -> bool {
-        self.0.iter().all(|e| e.can_be(action))
+        self.active().all(|e| e.can_be(action))
     }
 
-    pub fn on_be(&self, action: &Action) {
-        self.0.iter().for_each(|e| e.on_do(action))
+    pub fn on_be(&self, board: &mut Board, action: &Action) {
+        self.active().for_each(|e| e.on_be(board, action))
     }
 
     pub fn pre_tick(&mut self, time: &Time) {
         self.0.iter_mut().for_each(|e| e.pre_tick(time))
     }
 
+    /// Drops the effects whose time ran out, after letting them act one last time.
     pub fn post_tick(&mut self, time: &Time) {
-        self.0.iter_mut().for_each(|e| e.post_tick(time))
+        self.0.iter_mut().for_each(|e| e.post_tick(time));
+        self.0.retain(|e| !e.time().is_zero());
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub struct Properties(pub Vec<Property>);
 
 impl Properties {
@@ -1039,3 +1243,119 @@ impl Properties {
             .sum()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recolor_pieces() {
+        let knight = Piece::knight(Color::White).with_color(Color::Black);
+        assert_eq!(knight, Piece::knight(Color::Black));
+        assert_eq!(knight.name(), "Knight");
+        assert_eq!(Piece::None.with_color(Color::Black), Piece::None);
+    }
+
+    #[test]
+    fn effects_refresh_instead_of_stacking() {
+        let mut data = PieceData::new(Color::White, vec![Type::Biologic]);
+        data.add_effect(Effect::Fire(Time::rounds(2)));
+        data.add_effect(Effect::fire());
+        data.add_effect(Effect::Fire(Time::rounds(1)));
+        assert_eq!(data.effects.0, vec![Effect::Fire(Time::rounds(5))]);
+    }
+
+    #[test]
+    fn effects_expire() {
+        let mut data = PieceData::new(Color::White, vec![Type::Biologic]);
+        data.add_effect(Effect::ice());
+        data.add_effect(Effect::Fire(Time::turns(1)));
+        data.cooldown = Time::rounds(2);
+        data.tick(&Time::turns(1));
+        assert_eq!(data.effects.0, vec![Effect::ice()]);
+        for _ in 0..2 {
+            data.tick(&Time::rounds(1));
+        }
+        assert_eq!(data.effects.0, vec![Effect::Ice(Time::rounds(1))]);
+        assert!(data.cooldown.is_zero());
+        data.tick(&Time::rounds(1));
+        assert!(data.effects.0.is_empty());
+    }
+
+    #[test]
+    fn effect_kinds_ignore_the_time() {
+        let mut data = PieceData::new(Color::White, vec![Type::Biologic]);
+        data.add_effect(Effect::Fire(Time::rounds(2)));
+        assert!(!data.has_effect(&Effect::fire()));
+        assert!(data.has_effect_kind(mem::discriminant(&Effect::fire())));
+        assert!(!data.has_effect_kind(mem::discriminant(&Effect::ice())));
+    }
+
+    #[test]
+    fn invulnerability_suppresses_deactivate() {
+        let action = Action::r#move(&Pos::new(0, 0), &Pos::new(0, 1));
+        let mut data = PieceData::new(Color::White, vec![Type::Biologic]);
+        data.add_effect(Effect::deactivate());
+        assert!(!data.can_do(&action));
+        data.add_effect(Effect::invulnerability());
+        assert!(data.can_do(&action));
+        assert!(!data.can_be(&action));
+        assert_eq!(data.effects.0.len(), 2);
+    }
+
+    #[test]
+    fn weights() {
+        assert_eq!(Piece::pawn(Color::White).weight(), Some(2));
+        assert_eq!(Piece::rook(Color::White).weight(), None);
+        assert_eq!(Piece::None.weight(), None);
+        assert!(Piece::pawn(Color::Black).is_transportable(&2));
+        assert!(!Piece::pawn(Color::Black).is_transportable(&1));
+    }
+
+    #[test]
+    fn magician_ability_area() {
+        let board = Board::default();
+        let magician = Piece::magician(Color::White);
+        let mut area = magician.ability_area(&board, &Pos::new(2, 5), &Info::Integer(0));
+        area.sort_by_key(|pos| (pos.x, pos.y));
+        let expected = (0..=6)
+            .flat_map(|x| (1..=7).map(move |y| Pos::new(x, y)))
+            .filter(|pos| pos != &Pos::new(2, 5))
+            .collect::<Vec<_>>();
+        assert_eq!(area, expected);
+
+        let king = Piece::king(Color::White);
+        let to = Pos::new(4, 4);
+        assert_eq!(
+            king.ability_area(&board, &Pos::new(0, 0), &Info::Pos(to.clone())),
+            vec![to]
+        );
+        assert!(Piece::pawn(Color::White)
+            .ability_area(&board, &Pos::new(0, 0), &Info::Integer(0))
+            .is_empty());
+    }
+
+    #[test]
+    fn ranged_pieces_do_not_attack_themselves() {
+        for piece in [Piece::archer(Color::White), Piece::cannon(Color::White)] {
+            let from = Pos::new(3, 3);
+            let mut board = Board::default();
+            board.get_mut(&from).unwrap().replace(piece.clone());
+            board
+                .get_mut(&Pos::new(4, 4))
+                .unwrap()
+                .replace(Piece::pawn(Color::Black));
+            assert!(!piece.can_do(&board, Action::attack(&from, &from)));
+            assert!(piece.can_do(&board, Action::attack(&from, &Pos::new(4, 4))));
+            let attacked = board
+                .actions_from(&from)
+                .into_iter()
+                .filter_map(|action| match action {
+                    Action::Attack { to, .. } => Some(to),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(attacked, vec![Pos::new(4, 4)]);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//!
+//! Steps through a recorded game, to watch it again or spectate it from any point.
+use serde::{Deserialize, Serialize};
+
+use crate::{board::Board, Action};
+
+/// Why `ReplayPlayer::step_forward` didn't make an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepError {
+    /// The whole log was already played.
+    End,
+    /// The board refused the next action, the log doesn't fit the start.
+    Refused,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayPlayer {
+    /// The board before the first action of the log.
+    start: Board,
+    pub board: Board,
+    pub log: Vec<Action>,
+    /// How many actions of the log have been made on the board.
+    pub index: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(start: Board, log: Vec<Action>) -> Self {
+        Self {
+            board: start.clone(),
+            start,
+            log,
+            index: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log.is_empty()
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.index == self.log.len()
+    }
+
+    /// Makes the next action, ticking the board like a click does. A refused action leaves
+    /// the board and the index as they were.
+    pub fn step_forward(&mut self) -> Result<(), StepError> {
+        let action = self.log.get(self.index).ok_or(StepError::End)?;
+        if !self.board.make(action.clone()) {
+            return Err(StepError::Refused);
+        }
+        self.board.tick();
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Undoes the last action, return false if at the start.
+    pub fn step_back(&mut self) -> bool {
+        if self.index == 0 {
+            return false;
+        }
+        self.seek(self.index - 1);
+        true
+    }
+
+    /// Shows the board after the first `index` actions, or after the whole log if there
+    /// aren't that many. Going back replays the log from the start, which stops early at
+    /// a refused action.
+    pub fn seek(&mut self, index: usize) {
+        let index = index.min(self.log.len());
+        if index < self.index {
+            self.board = self.start.clone();
+            self.index = 0;
+        }
+        while self.index < index && self.step_forward().is_ok() {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Pos;
+
+    fn knight_dance() -> Vec<Action> {
+        [
+            ((1, 0), (2, 2)),
+            ((1, 7), (2, 5)),
+            ((6, 0), (5, 2)),
+            ((6, 7), (5, 5)),
+        ]
+        .into_iter()
+        .map(|((fx, fy), (tx, ty))| Action::r#move(&Pos::new(fx, fy), &Pos::new(tx, ty)))
+        .collect()
+    }
+
+    #[test]
+    fn seeking_replays_the_game() {
+        let start = Board::default_chessboard();
+        let log = knight_dance();
+        let mut positions = vec![start.clone()];
+        let mut board = start.clone();
+        for action in log.iter() {
+            board.make(action.clone());
+            board.tick();
+            positions.push(board.clone());
+        }
+
+        let mut replay = ReplayPlayer::new(start, log);
+        assert_eq!(replay.len(), 4);
+        replay.seek(2);
+        assert_eq!(replay.board, positions[2]);
+        replay.seek(100);
+        assert!(replay.is_at_end());
+        assert_eq!(replay.board, positions[4]);
+        assert_eq!(replay.step_forward(), Err(StepError::End));
+
+        assert!(replay.step_back());
+        assert_eq!(replay.index, 3);
+        assert_eq!(replay.board, positions[3]);
+        replay.seek(0);
+        assert_eq!(replay.board, positions[0]);
+        assert!(!replay.step_back());
+        assert_eq!(replay.step_forward(), Ok(()));
+        assert_eq!(replay.board, positions[1]);
+    }
+
+    #[test]
+    fn refused_actions_stop_the_replay() {
+        let start = Board::default_chessboard();
+        let mut log = knight_dance();
+        // nothing to take on an empty tile
+        log.insert(1, Action::take(&Pos::new(0, 0), &Pos::new(4, 4)));
+        let mut after_first = start.clone();
+        after_first.make(log[0].clone());
+        after_first.tick();
+
+        let mut replay = ReplayPlayer::new(start, log);
+        assert_eq!(replay.step_forward(), Ok(()));
+        assert_eq!(replay.step_forward(), Err(StepError::Refused));
+        assert_eq!(replay.index, 1);
+        assert_eq!(replay.board, after_first);
+
+        replay.seek(0);
+        replay.seek(4);
+        assert_eq!(replay.index, 1);
+        assert_eq!(replay.board, after_first);
+    }
+}
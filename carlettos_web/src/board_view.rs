@@ -0,0 +1,173 @@
+//!
+//! A common view over the two chess engines, so the UI can draw either of them.
+use carlettos_chess::{chess_controller::CChess, prelude::Piece, Pos};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    Move,
+    Take,
+    Attack,
+    TakeAttack,
+}
+
+impl Highlight {
+    pub fn class(&self) -> &'static str {
+        match self {
+            Highlight::None => "",
+            Highlight::Move => "move",
+            Highlight::Take => "take",
+            Highlight::Attack => "attack",
+            Highlight::TakeAttack => "take-attack",
+        }
+    }
+}
+
+/// The squares are given as `(x, y)`, with `(0, 0)` at the top left of the screen.
+pub trait BoardView {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    /// Name of the image of the piece in the square, if any.
+    fn piece_at(&self, square: (usize, usize)) -> Option<String>;
+    fn highlight_at(&self, square: (usize, usize)) -> Highlight;
+    fn click(&mut self, square: (usize, usize));
+}
+
+impl BoardView for chess_api::Board {
+    fn width(&self) -> usize {
+        8
+    }
+
+    fn height(&self) -> usize {
+        8
+    }
+
+    fn piece_at(&self, square: (usize, usize)) -> Option<String> {
+        self.get(&square).as_ref().map(|piece| piece.class_name())
+    }
+
+    fn highlight_at(&self, square: (usize, usize)) -> Highlight {
+        if self.take_squares.contains(&square) {
+            Highlight::Take
+        } else if self.move_squares.contains(&square) {
+            Highlight::Move
+        } else {
+            Highlight::None
+        }
+    }
+
+    fn click(&mut self, square: (usize, usize)) {
+        self.on_click(square);
+    }
+}
+
+/// The row 0 of the screen is the last row of the board.
+fn pos_of(chess: &CChess, (x, y): (usize, usize)) -> Option<Pos> {
+    let y = chess.height().checked_sub(y + 1)?;
+    Some(Pos::new(x, y))
+}
+
+/// The square of the `BoardView` showing the tile at `pos`, the inverse of `pos_of`.
+pub fn square_of(chess: &CChess, pos: &Pos) -> (usize, usize) {
+    (pos.x, chess.height().saturating_sub(pos.y + 1))
+}
+
+impl BoardView for CChess {
+    fn width(&self) -> usize {
+        CChess::width(self)
+    }
+
+    fn height(&self) -> usize {
+        CChess::height(self)
+    }
+
+    fn piece_at(&self, square: (usize, usize)) -> Option<String> {
+        let pos = pos_of(self, square)?;
+        match &self.board.get(&pos)?.piece {
+            Piece::None => None,
+            piece => Some(piece_name(piece)),
+        }
+    }
+
+    fn highlight_at(&self, square: (usize, usize)) -> Highlight {
+        let Some(pos) = pos_of(self, square) else {
+            return Highlight::None;
+        };
+        match (
+            self.has_move(&pos),
+            self.has_take(&pos),
+            self.has_attack(&pos),
+        ) {
+            (true, _, _) => Highlight::Move,
+            (_, true, true) => Highlight::TakeAttack,
+            (_, true, _) => Highlight::Take,
+            (_, _, true) => Highlight::Attack,
+            _ => Highlight::None,
+        }
+    }
+
+    fn click(&mut self, square: (usize, usize)) {
+        if let Some(pos) = pos_of(self, square) {
+            CChess::click(self, pos);
+        }
+    }
+}
+
+pub fn piece_name(piece: &Piece) -> String {
+    match piece {
+        Piece::None => "".to_string(),
+        Piece::Pawn(data) => format!("{:?}_pawn", data.color).to_lowercase(),
+        Piece::Knight(data) => format!("{:?}_knight", data.color).to_lowercase(),
+        Piece::Bishop(data) => format!("{:?}_bishop", data.color).to_lowercase(),
+        Piece::Rook(data) => format!("{:?}_rook", data.color).to_lowercase(),
+        Piece::Queen(data) => format!("{:?}_queen", data.color).to_lowercase(),
+        Piece::King(data) => format!("{:?}_king", data.color).to_lowercase(),
+        Piece::Archer(data) => format!("{:?}_archer", data.color).to_lowercase(),
+        Piece::Ballista(data) => format!("{:?}_ballista", data.color).to_lowercase(),
+        Piece::Builder(data) => format!("{:?}_builder", data.color).to_lowercase(),
+        Piece::Cannon(data) => format!("{:?}_cannon", data.color).to_lowercase(),
+        Piece::Catapult(data) => format!("{:?}_catapult", data.color).to_lowercase(),
+        Piece::CrazyPawn(data) => format!("{:?}_crazy_pawn", data.color).to_lowercase(),
+        Piece::Magician(data) => format!("{:?}_magician", data.color).to_lowercase(),
+        Piece::Paladin(data) => format!("{:?}_paladin", data.color).to_lowercase(),
+        Piece::Ram(data) => format!("{:?}_ram", data.color).to_lowercase(),
+        Piece::ShieldBearer(data) => format!("{:?}_shield_bearer", data.color).to_lowercase(),
+        Piece::Ship(data) => format!("{:?}_ship", data.color).to_lowercase(),
+        Piece::SuperPawn(data) => format!("{:?}_super_pawn", data.color).to_lowercase(),
+        Piece::TeslaTower(data) => format!("{:?}_tesla_tower", data.color).to_lowercase(),
+        Piece::Wall(data) => format!("{:?}_wall", data.color).to_lowercase(),
+        Piece::Warlock(data) => format!("{:?}_warlock", data.color).to_lowercase(),
+        Piece::Portal(data) => format!("{:?}_portal", data.color).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dimensions<B: BoardView>(board: &B) -> (usize, usize) {
+        (board.width(), board.height())
+    }
+
+    #[test]
+    fn both_engines_are_board_views() {
+        let chess = chess_api::Board::default();
+        assert_eq!(dimensions(&chess), (8, 8));
+        assert_eq!(chess.piece_at((0, 0)), Some("black_rook".to_string()));
+        assert_eq!(chess.piece_at((3, 4)), None);
+
+        let cchess = CChess::cchessboard();
+        assert_eq!(dimensions(&cchess), (16, 17));
+        assert_eq!(cchess.piece_at((0, 0)), Some("black_cannon".to_string()));
+        assert_eq!(cchess.piece_at((0, 16)), Some("white_cannon".to_string()));
+        assert_eq!(cchess.piece_at((0, 17)), None);
+
+        let mut cchess = CChess::default_chessboard();
+        assert_eq!(dimensions(&cchess), (8, 8));
+        BoardView::click(&mut cchess, (0, 6));
+        assert_eq!(cchess.highlight_at((0, 5)), Highlight::Move);
+        let pos = Pos::new(0, 2);
+        assert_eq!(square_of(&cchess, &pos), (0, 5));
+        assert_eq!(pos_of(&cchess, square_of(&cchess, &pos)), Some(pos));
+    }
+}
@@ -7,19 +7,38 @@ pub enum State {
 #[derive(Clone, Copy)]
 pub struct Cell {
     pub state: State,
+    /// Generations this cell has stayed alive.
+    pub age: u32,
 }
 
 impl Cell {
     pub fn new_dead() -> Self {
-        Cell { state: State::Dead }
+        Cell {
+            state: State::Dead,
+            age: 0,
+        }
     }
 
     pub fn set_alive(&mut self) {
         self.state = State::Alive;
+        self.age = 0;
     }
 
     pub fn set_dead(&mut self) {
         self.state = State::Dead;
+        self.age = 0;
+    }
+
+    pub fn grow_older(&mut self) {
+        self.age += 1;
+    }
+
+    pub fn age_class(&self) -> &'static str {
+        match self.age {
+            0 => "cell-age-new",
+            1..=9 => "cell-age-young",
+            _ => "cell-age-old",
+        }
     }
 
     pub fn is_alive(&self) -> bool {
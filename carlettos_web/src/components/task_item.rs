@@ -1,4 +1,8 @@
-use yew::{classes, function_component, html, Callback, Html, Properties};
+use web_sys::HtmlInputElement;
+use yew::{
+    classes, function_component, html, use_node_ref, use_state, Callback, Html, KeyboardEvent,
+    Properties,
+};
 
 use crate::models::Task;
 
@@ -7,6 +11,7 @@ pub struct TaskItemProps {
     pub task: Task,
     pub on_delete_task: Callback<String>,
     pub on_toggle_task: Callback<String>,
+    pub on_rename_task: Callback<(String, String)>,
 }
 
 #[function_component(TaskItem)]
@@ -15,8 +20,12 @@ pub fn task(
         task,
         on_delete_task,
         on_toggle_task,
+        on_rename_task,
     }: &TaskItemProps,
 ) -> Html {
+    let editing = use_state(|| false);
+    let input_node_ref = use_node_ref();
+
     let list_item_class = match task.completed {
         true => Some("completed"),
         false => None,
@@ -34,10 +43,41 @@ pub fn task(
         move |_| on_toggle_task.emit(task.id.clone())
     };
 
+    let on_edit = {
+        let editing = editing.clone();
+        move |_| editing.set(true)
+    };
+
+    let on_rename = {
+        let task = task.clone();
+        let editing = editing.clone();
+        let input_node_ref = input_node_ref.clone();
+        let on_rename_task = on_rename_task.clone();
+        Callback::from(move |kbe: KeyboardEvent| match kbe.key().as_str() {
+            "Enter" => {
+                if let Some(input) = input_node_ref.cast::<HtmlInputElement>() {
+                    let title = input.value().trim().to_string();
+                    if !title.is_empty() && title != task.title {
+                        on_rename_task.emit((task.id.clone(), title));
+                    }
+                }
+                editing.set(false);
+            }
+            "Escape" => editing.set(false),
+            _ => (),
+        })
+    };
+
+    let title = if *editing {
+        html! { <input ref={input_node_ref} type="text" value={task.title.clone()} onkeydown={on_rename} /> }
+    } else {
+        html! { <label ondblclick={on_edit}>{&task.title}</label> }
+    };
+
     html! {
         <li class={classes!(list_item_class, "center")}>
             <input type="checkbox" checked={task.completed} onchange={on_toggle} />
-            <label>{&task.title}</label>
+            {title}
             <button onclick={on_delete_click}>
                 {"Delete"}
             </button>
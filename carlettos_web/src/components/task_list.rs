@@ -8,6 +8,7 @@ pub struct TaskListProps {
     pub tasks: Vec<Task>,
     pub on_delete_task: Callback<String>,
     pub on_toggle_task: Callback<String>,
+    pub on_rename_task: Callback<(String, String)>,
 }
 
 #[function_component(TaskList)]
@@ -16,11 +17,12 @@ pub fn task_list(
         tasks,
         on_delete_task,
         on_toggle_task,
+        on_rename_task,
     }: &TaskListProps,
 ) -> Html {
     let tasks: Html = tasks
         .iter()
-        .map(|task| html!( <TaskItem task={task.clone()} on_delete_task={on_delete_task} on_toggle_task={on_toggle_task} /> ))
+        .map(|task| html!( <TaskItem task={task.clone()} on_delete_task={on_delete_task} on_toggle_task={on_toggle_task} on_rename_task={on_rename_task} /> ))
         .collect();
 
     html!(
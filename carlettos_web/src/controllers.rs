@@ -1,5 +1,6 @@
 use carlettos_chess::Pos;
-use chess_api::{Board, Color};
+use chess_api::{Board, Piece};
+use gloo::timers::callback::Interval;
 use yew::UseReducerHandle;
 
 use crate::{
@@ -7,11 +8,14 @@ use crate::{
     models::Vote,
     state::{
         C2048LeaderboardAction, C2048LeaderboardState, CarlettosChessAction, CarlettosChessState,
-        ChessAction, ChessState, TaskAction, TaskState, VoteAction, VotesState,
+        ChessAction, ChessResult, ChessState, TaskAction, TaskFilter, TaskState, VoteAction,
+        VotesState,
     },
     sub_api,
 };
 
+pub const POLL_MILLIS: u32 = 200;
+
 pub struct ChessController {
     state: UseReducerHandle<ChessState>,
 }
@@ -38,17 +42,71 @@ impl ChessController {
     }
 
     pub fn on_click(&self, from: (usize, usize)) {
+        let Some(board) = self.state.click(from) else {
+            return;
+        };
         let chess = self.state.clone();
         wasm_bindgen_futures::spawn_local(async move {
-            let mut board = chess.board.clone();
-            board.on_click(from);
             let updated_board = sub_api::update_chess_game(board).await.unwrap();
             chess.dispatch(ChessAction::Update(updated_board))
         })
     }
 
-    pub fn winner(&self) -> &Option<Color> {
-        &self.state.winner
+    pub fn set_spectating(&self, spectating: bool) {
+        self.state.dispatch(ChessAction::Spectate(spectating));
+    }
+
+    /// Fetches the board every `POLL_MILLIS`, to follow the moves of the other side.
+    /// The polling stops when the returned interval is dropped.
+    pub fn poll(&self) -> Interval {
+        let chess = self.state.clone();
+        Interval::new(POLL_MILLIS, move || {
+            let chess = chess.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let fetched_board = sub_api::get_chess_game().await.unwrap();
+                chess.dispatch(ChessAction::Get(fetched_board))
+            })
+        })
+    }
+
+    /// Enters the read only mode, fetching the board every `POLL_MILLIS`.
+    /// The polling stops when the returned interval is dropped.
+    pub fn spectate(&self) -> Interval {
+        self.set_spectating(true);
+        self.poll()
+    }
+
+    pub fn spectating(&self) -> bool {
+        self.state.spectating
+    }
+
+    pub fn flip(&self) {
+        self.state.dispatch(ChessAction::Flip);
+    }
+
+    pub fn promote(&self, piece: Piece) {
+        let chess = self.state.clone();
+        let mut board = chess.board.clone();
+        if chess.spectating || !board.promote(piece.clone()) {
+            return;
+        }
+        chess.dispatch(ChessAction::Promote(piece));
+        wasm_bindgen_futures::spawn_local(async move {
+            let updated_board = sub_api::update_chess_game(board).await.unwrap();
+            chess.dispatch(ChessAction::Update(updated_board))
+        })
+    }
+
+    pub fn pending_promotion(&self) -> &Option<(usize, usize)> {
+        &self.state.pending_promotion
+    }
+
+    pub fn result(&self) -> &Option<ChessResult> {
+        &self.state.result
+    }
+
+    pub fn pgn(&self) -> Option<String> {
+        self.state.pgn()
     }
 }
 
@@ -76,6 +134,11 @@ impl CarlettosChessController {
         let chess = self.state.clone();
         chess.dispatch(CarlettosChessAction::DisplayClick(from));
     }
+
+    pub fn flip(&self) {
+        let chess = self.state.clone();
+        chess.dispatch(CarlettosChessAction::Flip);
+    }
 }
 
 pub struct TaskController {
@@ -105,10 +168,20 @@ impl TaskController {
 
     pub fn toggle_task(&self, id: String) {
         let tasks = self.state.clone();
+        tasks.dispatch(TaskAction::Toggle(id.clone()));
         wasm_bindgen_futures::spawn_local(async move {
-            let response = sub_api::toggle_task(id.clone()).await.unwrap();
+            let response = sub_api::toggle_task(id.clone()).await;
+            let confirmed = matches!(response, Ok(response) if response.rows_affected == 1);
+            tasks.dispatch(TaskAction::ToggleConfirm(id, confirmed));
+        });
+    }
+
+    pub fn rename_task(&self, id: String, title: String) {
+        let tasks = self.state.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let response = sub_api::rename_task(id.clone(), &title).await.unwrap();
             if response.rows_affected == 1 {
-                tasks.dispatch(TaskAction::Toggle(id.clone()));
+                tasks.dispatch(TaskAction::Rename(id, title));
             }
         });
     }
@@ -122,6 +195,28 @@ impl TaskController {
             }
         });
     }
+
+    pub fn clear_completed(&self) {
+        let tasks = self.state.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let response = sub_api::clear_completed().await.unwrap();
+            if response.rows_affected == tasks.completed_count() as u64 {
+                tasks.dispatch(TaskAction::ClearCompleted);
+            } else {
+                // the server knew of other completed tasks, or missed some of ours
+                let fetched_tasks = sub_api::fetch_tasks().await.unwrap();
+                tasks.dispatch(TaskAction::Set(fetched_tasks));
+            }
+        });
+    }
+
+    pub fn set_filter(&self, filter: TaskFilter) {
+        self.state.dispatch(TaskAction::Filter(filter));
+    }
+
+    pub fn search(&self, search: String) {
+        self.state.dispatch(TaskAction::Search(search));
+    }
 }
 
 pub struct VotesController {
@@ -183,12 +278,19 @@ impl C2048LeaderboardController {
 
     pub fn get_highscores(&self) {
         let state = self.state.clone();
+        let generation = state.generation + 1;
+        state.dispatch(C2048LeaderboardAction::Fetch);
         wasm_bindgen_futures::spawn_local(async move {
             let highscores = sub_api::get_highscores().await.unwrap();
-            state.dispatch(C2048LeaderboardAction::Load(highscores));
+            state.dispatch(C2048LeaderboardAction::Load(generation, highscores));
         })
     }
 
+    /// Outdates the fetches in flight, so their responses are ignored.
+    pub fn cancel(&self) {
+        self.state.dispatch(C2048LeaderboardAction::Fetch);
+    }
+
     pub fn add_highscore(&self, entry: Entry) {
         let state = self.state.clone();
         wasm_bindgen_futures::spawn_local(async move {
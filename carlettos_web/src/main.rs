@@ -1,6 +1,7 @@
 use yew::{html::Scope, prelude::*};
 use yew_router::prelude::*;
 
+mod board_view;
 mod controllers;
 mod models;
 mod state;
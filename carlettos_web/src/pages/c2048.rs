@@ -1,4 +1,4 @@
-use std::ops::AddAssign;
+use std::{cmp::Reverse, ops::AddAssign};
 
 use c2048_leaderboard::C2048Leaderboard;
 use csta::prelude::*;
@@ -7,6 +7,8 @@ use rand::prelude::*;
 use yew::prelude::*;
 
 const L: usize = 4;
+/// Moves played by a single press of the solve button.
+const AUTO_SOLVE_MOVES: usize = 1000;
 
 pub mod c2048_leaderboard;
 
@@ -122,6 +124,7 @@ pub enum C2048Msg {
     SeeXi,
     Nothing,
     Automove,
+    AutoSolve,
     Reset,
     Touch((i32, i32)),
     TouchEnd,
@@ -136,6 +139,24 @@ pub enum Mode {
     Xi,
 }
 
+/// Chances of spawning a 4 (exp 2) instead of a 2 (exp 1).
+#[derive(Debug, Clone)]
+pub struct SpawnConfig {
+    /// Used for the tile spawned after each move.
+    pub two_chance: f64,
+    /// Used for the two tiles at the start of the game.
+    pub start_two_chance: f64,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        Self {
+            two_chance: 0.1,
+            start_two_chance: 0.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct C2048 {
     pub grid: [Tile; L * L],
@@ -148,6 +169,7 @@ pub struct C2048 {
     pub show_leaderboard: bool,
     pub energies: Vec<isize>,
     pub score: usize,
+    pub spawn: SpawnConfig,
 }
 
 impl Randomizable for C2048 {
@@ -158,14 +180,25 @@ impl Randomizable for C2048 {
 
 impl C2048 {
     pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
-        let mut c2048 = Self::default();
-        c2048.spawn_tile(rng, 0.0);
-        c2048.spawn_tile(rng, 0.0);
+        Self::with_spawn_config(rng, SpawnConfig::default())
+    }
+
+    pub fn with_spawn_config<R: Rng + ?Sized>(rng: &mut R, spawn: SpawnConfig) -> Self {
+        let mut c2048 = Self {
+            spawn,
+            ..Default::default()
+        };
+        c2048.spawn_tile_with_chance(rng, c2048.spawn.start_two_chance);
+        c2048.spawn_tile_with_chance(rng, c2048.spawn.start_two_chance);
         c2048.energies.push(c2048.energy().sum());
         c2048
     }
 
-    pub fn spawn_tile<R: Rng + ?Sized>(&mut self, rng: &mut R, chance: f64) {
+    pub fn spawn_tile<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.spawn_tile_with_chance(rng, self.spawn.two_chance);
+    }
+
+    fn spawn_tile_with_chance<R: Rng + ?Sized>(&mut self, rng: &mut R, chance: f64) {
         self.energies.push(self.energy().sum());
         let random_exp = if rng.gen_bool(chance) { 2 } else { 1 };
 
@@ -193,6 +226,10 @@ impl C2048 {
         self.grid.iter().max().unwrap()
     }
 
+    pub fn empty_tiles(&self) -> usize {
+        self.grid.iter().filter(|tile| tile.exp == 0).count()
+    }
+
     pub fn is_lose(&self) -> bool {
         if self.grid.iter().any(|tile| tile.exp == 0) {
             return false;
@@ -301,6 +338,11 @@ impl C2048 {
         self.full_energy_at(i).reduce()
     }
 
+    /// The full energy of every tile, indexed as `[y][x]`.
+    pub fn energy_grid(&self) -> [[FullEnergy; L]; L] {
+        std::array::from_fn(|y| std::array::from_fn(|x| self.full_energy_at(x + y * L)))
+    }
+
     pub fn energy(&self) -> Energy {
         let mut energy = Energy::default();
         for x in 0..L {
@@ -333,6 +375,32 @@ impl C2048 {
         clone
     }
 
+    /// The game after the move chosen by `Automove`, the one leaving the lowest energy.
+    /// Ties are broken by the move leaving more empty tiles, and then by the order down, up, left, right.
+    pub fn auto_move(&self) -> Option<Self> {
+        [Move::Down, Move::Up, Move::Left, Move::Right]
+            .into_iter()
+            .map(|mv| self.clone_move(mv))
+            .filter(|game| game.has_moved)
+            .min_by_key(|game| (game.energy().sum(), Reverse(game.empty_tiles())))
+    }
+
+    /// Plays the `auto_move` until losing or until `max_moves`, returning the number of moves played.
+    pub fn auto_solve<R: Rng + ?Sized>(&mut self, rng: &mut R, max_moves: usize) -> usize {
+        let mut moves = 0;
+        while moves < max_moves {
+            let Some(next) = self.auto_move() else {
+                break;
+            };
+            *self = next;
+            self.automoved = true;
+            self.spawn_tile(rng);
+            self.reset();
+            moves += 1;
+        }
+        moves
+    }
+
     pub fn left(&mut self) {
         for y in 0..L {
             for x in 1..L {
@@ -401,8 +469,7 @@ impl C2048 {
         }
     }
 
-    fn show_energy(&self, id: usize) -> Html {
-        let e = self.full_energy_at(id);
+    fn show_energy(e: &FullEnergy) -> Html {
         html! {
             <div class="show-energy">
                 <div class="energy-epsilon">
@@ -475,7 +542,7 @@ impl Component for C2048 {
                     Move::Up => self.up(),
                 }
                 if self.has_moved {
-                    self.spawn_tile(&mut thread_rng(), 0.1);
+                    self.spawn_tile(&mut thread_rng());
                     self.reset();
                     if self.is_lose() && !self.automoved {
                         self.show_leaderboard = true;
@@ -496,7 +563,7 @@ impl Component for C2048 {
                             false => self.down(),
                         }
                         if self.has_moved {
-                            self.spawn_tile(&mut thread_rng(), 0.1);
+                            self.spawn_tile(&mut thread_rng());
                             self.reset();
                             if self.is_lose() && !self.automoved {
                                 self.show_leaderboard = true;
@@ -540,23 +607,23 @@ impl Component for C2048 {
                 };
             }
             C2048Msg::Automove => {
-                let down = self.clone_move(Move::Down);
-                let up = self.clone_move(Move::Up);
-                let left = self.clone_move(Move::Left);
-                let right = self.clone_move(Move::Right);
-                let moves = vec![down, up, left, right];
-                let min = moves.into_iter().filter(|g| g.has_moved).min();
-                if let Some(min) = min {
+                if let Some(min) = self.auto_move() {
                     self.automoved = true;
                     *self = min;
-                    self.spawn_tile(&mut thread_rng(), 0.1);
+                    self.spawn_tile(&mut thread_rng());
                     self.reset();
                     self.selected = None;
                     self.mode = Mode::None;
                 }
             }
+            C2048Msg::AutoSolve => {
+                if self.auto_solve(&mut thread_rng(), AUTO_SOLVE_MOVES) > 0 {
+                    self.selected = None;
+                    self.mode = Mode::None;
+                }
+            }
             C2048Msg::Reset => {
-                *self = Self::new(&mut thread_rng());
+                *self = Self::with_spawn_config(&mut thread_rng(), self.spawn.clone());
                 self.automoved = false;
             }
             C2048Msg::TouchEnd => {
@@ -568,26 +635,23 @@ impl Component for C2048 {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        let energies = self.energy_grid();
         let game = self.grid.chunks(L).enumerate().map(|(y, row)| {
             let offset = y * L;
 
             let row = row.iter().enumerate().map(|(x, tile)| {
                 let number: usize = 1 << tile.exp;
                 let i = x + offset;
+                let energy = &energies[y][x];
 
                 let tile = match self.mode {
                     Mode::None => match self.selected {
-                        Some(id) if id == i => self.show_energy(id),
+                        Some(id) if id == i => Self::show_energy(energy),
                         _ if number != 1 => html! { number },
                         _ => html! {}
                     },
-                    Mode::Epsilon => {
-                        let energy = self.full_energy_at(i);
-                        html! { { energy.epsilon } }
-                    },
+                    Mode::Epsilon => html! { { energy.epsilon } },
                     Mode::Phi => {
-                        let energy = self.full_energy_at(i);
-
                         html! {
                             <div class="show-energy">
                                 <div class={classes!("energy-phi", "phi-up", "only-phi")}>
@@ -606,15 +670,13 @@ impl Component for C2048 {
                         }
                     },
                     Mode::Xi => {
-                        let e = self.full_energy_at(i);
-                        
                         html! {
                             <div class="show-energy">
                                 <div class={classes!("energy-xi", "xi-vertical")}>
-                                    { e.xi_vertical } { if e.xi_vertical.is_some() { "↑" } else { "" } }
+                                    { energy.xi_vertical } { if energy.xi_vertical.is_some() { "↑" } else { "" } }
                                 </div>
                                 <div class={classes!("energy-xi", "xi-horizontal")}>
-                                    { e.xi_horizontal } { if e.xi_horizontal.is_some() { "→" } else { "" } }
+                                    { energy.xi_horizontal } { if energy.xi_horizontal.is_some() { "→" } else { "" } }
                                 </div>
                             </div>
                         }
@@ -648,6 +710,8 @@ impl Component for C2048 {
                 C2048Msg::Move(Move::Left)
             } else if kbe.key() == *"P" || kbe.key() == *"p" {
                 C2048Msg::Automove
+            } else if kbe.key() == *"O" || kbe.key() == *"o" {
+                C2048Msg::AutoSolve
             } else if kbe.key() == *"R" || kbe.key() == *"r" {
                 C2048Msg::Reset
             } else {
@@ -684,6 +748,7 @@ impl Component for C2048 {
                     </div>
                     <div class="c2048-buttons">
                         <button class="c2048-button" onclick={ctx.link().callback(|_| C2048Msg::Automove)}>{ "auto" }</button>
+                        <button class="c2048-button" onclick={ctx.link().callback(|_| C2048Msg::AutoSolve)}>{ "solve" }</button>
                         <button class="c2048-button" onclick={ctx.link().callback(|_| C2048Msg::Reset)}>{ "reset" }</button>
                     </div>
                     <div class="c2048-energy-container">
@@ -706,3 +771,76 @@ impl Component for C2048 {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn auto_move_tie_break() {
+        // A lone tile in a corner can go up or right with the same energy and empty tiles.
+        let mut game = C2048::default();
+        game._set_tile(0, 1);
+        let up = game.clone_move(Move::Up);
+        let right = game.clone_move(Move::Right);
+        assert_eq!(up.energy().sum(), right.energy().sum());
+        assert_eq!(up.empty_tiles(), right.empty_tiles());
+
+        let auto = game.auto_move().unwrap();
+        assert_eq!(auto.grid[L * (L - 1)].exp, 1);
+        assert_eq!(auto.grid[L - 1].exp, 0);
+    }
+
+    #[test]
+    fn energy_grid_sums_to_energy() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut game = C2048::new(&mut rng);
+        game.auto_solve(&mut rng, 30);
+
+        let mut energy = Energy::default();
+        for row in game.energy_grid() {
+            for cell in row {
+                energy += cell.reduce();
+            }
+        }
+        assert_eq!(energy, game.energy());
+    }
+
+    #[test]
+    fn spawn_config_two_chance() {
+        let spawn = SpawnConfig {
+            two_chance: 1.0,
+            start_two_chance: 1.0,
+        };
+        let mut rng = StdRng::seed_from_u64(16);
+        let mut game = C2048::with_spawn_config(&mut rng, spawn);
+        for _ in 0..6 {
+            game.spawn_tile(&mut rng);
+        }
+        assert_eq!(game.empty_tiles(), L * L - 8);
+        assert!(game
+            .grid
+            .iter()
+            .filter(|tile| tile.exp != 0)
+            .all(|tile| tile.exp == 2));
+    }
+
+    #[test]
+    fn auto_solve_seeded() {
+        let mut rng = StdRng::seed_from_u64(2048);
+        let mut game = C2048::new(&mut rng);
+        let moves = game.auto_solve(&mut rng, 10_000);
+        assert!(moves > 10 && moves < 10_000);
+        assert!(game.is_lose());
+
+        let mut rng = StdRng::seed_from_u64(2048);
+        let mut other = C2048::new(&mut rng);
+        assert_eq!(other.auto_solve(&mut rng, 10_000), moves);
+        assert_eq!(other.grid, game.grid);
+        assert_eq!(other.score, game.score);
+
+        let mut rng = StdRng::seed_from_u64(2048);
+        let mut capped = C2048::new(&mut rng);
+        assert_eq!(capped.auto_solve(&mut rng, 5), 5);
+    }
+}
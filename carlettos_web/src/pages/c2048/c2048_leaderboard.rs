@@ -34,6 +34,21 @@ impl Entry {
         }
     }
 
+    pub const MAX_NAME_LEN: usize = 16;
+
+    /// Trims the name and keeps its first `MAX_NAME_LEN` printable characters.
+    pub fn sanitize_name(name: &str) -> String {
+        let name: String = name
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect::<String>()
+            .trim()
+            .chars()
+            .take(Self::MAX_NAME_LEN)
+            .collect();
+        name.trim_end().to_string()
+    }
+
     pub fn to_table_row(&self) -> Html {
         html! {
             <tr>
@@ -73,7 +88,7 @@ pub fn c2048_leaderboard(
         let controller = controller.clone();
         use_effect_with((), move |_| {
             controller.get_highscores();
-            || ()
+            move || controller.cancel()
         });
     }
 
@@ -100,10 +115,10 @@ pub fn c2048_leaderboard(
                 .dyn_into::<HtmlInputElement>();
 
             if let Ok(input) = input {
-                add_hs.emit(Entry {
-                    name: input.value(),
-                    ..entry
-                });
+                let name = Entry::sanitize_name(&input.value());
+                if !name.is_empty() {
+                    add_hs.emit(Entry { name, ..entry });
+                }
                 input.set_value("");
             }
         })
@@ -120,10 +135,10 @@ pub fn c2048_leaderboard(
 
             if kbe.key() == *"Enter" {
                 if let Ok(input) = input {
-                    add_hs.emit(Entry {
-                        name: input.value(),
-                        ..entry
-                    });
+                    let name = Entry::sanitize_name(&input.value());
+                    if !name.is_empty() {
+                        add_hs.emit(Entry { name, ..entry });
+                    }
                     input.set_value("");
                 }
             }
@@ -150,7 +165,7 @@ pub fn c2048_leaderboard(
                         html!{
                             <thead>
                                 <tr>
-                                    <th> <input id="c2048_highscore_input" class="input c2048_highscore_input" type="text" placeholder="Put your name" onkeydown={on_enter} />
+                                    <th> <input id="c2048_highscore_input" class="input c2048_highscore_input" type="text" placeholder="Put your name" maxlength={Entry::MAX_NAME_LEN.to_string()} onkeydown={on_enter} />
                                     <button class="button" type="submit" style="height: 24px;" onclick={onclick}> { "Enter" } </button> </th>
                                     <th> { score } </th>
                                     <th> { max_tile } </th>
@@ -179,3 +194,23 @@ pub fn c2048_leaderboard(
         </section>
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sanitize_name() {
+        assert_eq!(Entry::sanitize_name("  carlettos \n"), "carlettos");
+        assert_eq!(
+            Entry::sanitize_name("a very long name for a leaderboard"),
+            "a very long name"
+        );
+        assert_eq!(
+            Entry::sanitize_name("fifteen chars x yz"),
+            "fifteen chars x"
+        );
+        assert_eq!(Entry::sanitize_name("ta\tb"), "tab");
+        assert_eq!(Entry::sanitize_name(" \u{7} "), "");
+    }
+}
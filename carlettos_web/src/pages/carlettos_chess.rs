@@ -3,29 +3,27 @@ use std::rc::Rc;
 use carlettos_chess::{chess_controller::CChess, prelude::*};
 use yew::prelude::*;
 
-use crate::{controllers::CarlettosChessController, state::CarlettosChessState};
+use crate::{
+    board_view::{square_of, BoardView, Highlight},
+    controllers::CarlettosChessController,
+    state::CarlettosChessState,
+};
 
 #[derive(Properties, PartialEq)]
 pub struct SquareProp {
-    board: Board,
-    piece: Piece,
+    piece: Option<String>,
+    highlight: Highlight,
     square: Pos,
     on_click: Callback<Pos>,
-    is_move: bool,
-    is_take: bool,
-    is_attack: bool,
 }
 
 #[function_component(ChessTile)]
 pub fn tile(
     SquareProp {
-        board: _,
         piece,
+        highlight,
         square,
         on_click,
-        is_move,
-        is_take,
-        is_attack,
     }: &SquareProp,
 ) -> Html {
     let idx = (square.x as u128) << 64 | square.y as u128;
@@ -36,54 +34,17 @@ pub fn tile(
         "square-white"
     };
 
-    let piece_name = match piece {
-        Piece::None => "".to_string(),
-        Piece::Pawn(data) => format!("{:?}_pawn", data.color).to_lowercase(),
-        Piece::Knight(data) => format!("{:?}_knight", data.color).to_lowercase(),
-        Piece::Bishop(data) => format!("{:?}_bishop", data.color).to_lowercase(),
-        Piece::Rook(data) => format!("{:?}_rook", data.color).to_lowercase(),
-        Piece::Queen(data) => format!("{:?}_queen", data.color).to_lowercase(),
-        Piece::King(data) => format!("{:?}_king", data.color).to_lowercase(),
-        Piece::Archer(data) => format!("{:?}_archer", data.color).to_lowercase(),
-        Piece::Ballista(data) => format!("{:?}_ballista", data.color).to_lowercase(),
-        Piece::Builder(data) => format!("{:?}_builder", data.color).to_lowercase(),
-        Piece::Cannon(data) => format!("{:?}_cannon", data.color).to_lowercase(),
-        Piece::Catapult(data) => format!("{:?}_catapult", data.color).to_lowercase(),
-        Piece::CrazyPawn(data) => format!("{:?}_crazy_pawn", data.color).to_lowercase(),
-        Piece::Magician(data) => format!("{:?}_magician", data.color).to_lowercase(),
-        Piece::Paladin(data) => format!("{:?}_paladin", data.color).to_lowercase(),
-        Piece::Ram(data) => format!("{:?}_ram", data.color).to_lowercase(),
-        Piece::ShieldBearer(data) => format!("{:?}_shield_bearer", data.color).to_lowercase(),
-        Piece::Ship(data) => format!("{:?}_ship", data.color).to_lowercase(),
-        Piece::SuperPawn(data) => format!("{:?}_super_pawn", data.color).to_lowercase(),
-        Piece::TeslaTower(data) => format!("{:?}_tesla_tower", data.color).to_lowercase(),
-        Piece::Wall(data) => format!("{:?}_wall", data.color).to_lowercase(),
-        Piece::Warlock(data) => format!("{:?}_warlock", data.color).to_lowercase(),
-        Piece::Portal(data) => format!("{:?}_portal", data.color).to_lowercase(),
-    };
-
     let on_square_click = {
         let square = square.clone();
         let on_click = on_click.clone();
         move |_| on_click.emit(square.clone())
     };
 
-    let status = if *is_move {
-        "move"
-    } else if *is_take && *is_attack {
-        "take-attack"
-    } else if *is_take {
-        "take"
-    } else if *is_attack {
-        "attack"
-    } else {
-        ""
-    };
-
-    let img_html = if matches!(piece, Piece::None) {
-        html! {}
-    } else {
-        html! { <img draggable={ "false" } class={classes!("carlettos-piece")} src={ format!("assets/{piece_name}.png") }/> }
+    let img_html = match piece {
+        Some(piece_name) => {
+            html! { <img draggable={ "false" } class={classes!("carlettos-piece")} src={ format!("assets/{piece_name}.png") }/> }
+        }
+        None => html! {},
     };
 
     html! {
@@ -91,7 +52,7 @@ pub fn tile(
             {
                 img_html
             }
-            <div class={classes!("carlettos-movement", status)}></div>
+            <div class={classes!("carlettos-movement", highlight.class())}></div>
         </div>
     }
 }
@@ -131,11 +92,17 @@ pub fn chess() -> Html {
         Callback::from(move |pos| chess_controller.on_display_click(pos))
     };
 
-    let rows = (0..chess.board.height()).rev().map(|row| {
+    let on_flip_click = {
+        let chess_controller = chess_controller.clone();
+        move |_| chess_controller.flip()
+    };
+
+    let rows = chess.board.display_rows(chess.flipped).into_iter().map(|row| {
         html! {
             <div class={classes!("carlettos-chess-row")}>
-                { for chess.board.row_iter(row).map(|tile| {
-                    html! { <ChessTile board={chess.board.board.clone()} piece={tile.piece.clone()} square={tile.pos().clone()} on_click={on_tile_click.clone()} is_move={chess.board.has_move(tile.pos())} is_take={chess.board.has_take(tile.pos())} is_attack={chess.board.has_attack(tile.pos())} /> }
+                { for chess.board.display_row(row, chess.flipped).into_iter().map(|tile| {
+                    let square = square_of(&chess.board, tile.pos());
+                    html! { <ChessTile piece={chess.board.piece_at(square)} highlight={chess.board.highlight_at(square)} square={tile.pos().clone()} on_click={on_tile_click.clone()} /> }
                 }) }
             </div>
         }});
@@ -145,6 +112,7 @@ pub fn chess() -> Html {
             <header>
                 <h1>{ "Carlettos Chess" }</h1>
                 <button onclick={on_button_click}>{ "Start" }</button>
+                <button onclick={on_flip_click}>{ "Flip" }</button>
             </header>
             <ChessPiecesDisplay display={chess.display.clone()} on_click={on_display_click} />
             <AboveChessHUD board={chess.board.board.clone()}/>
@@ -175,8 +143,12 @@ pub fn chess_pieces_display(
         html! {
             <div class={classes!("carlettos-chess-row")}>
                 { for display.row_iter(row).map(|tile| {
-                    let is_move = tile.pos() == &display.selected;
-                    html! { <ChessTile board={display.board.clone()} piece={tile.piece.clone()} square={tile.pos().clone()} on_click={on_click.clone()} is_move={is_move} is_take={false} is_attack={false} /> }
+                    let highlight = match tile.pos() == &display.selected {
+                        true => Highlight::Move,
+                        false => Highlight::None,
+                    };
+                    let square = square_of(display, tile.pos());
+                    html! { <ChessTile piece={display.piece_at(square)} highlight={highlight} square={tile.pos().clone()} on_click={on_click.clone()} /> }
                 }) }
             </div>
         }});
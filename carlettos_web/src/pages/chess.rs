@@ -1,15 +1,18 @@
 use std::rc::Rc;
 
 use chess_api::*;
-use gloo::timers::callback::Interval;
 use yew::prelude::*;
 
-use crate::{controllers::ChessController, state::ChessState};
+use crate::{
+    board_view::{BoardView, Highlight},
+    controllers::ChessController,
+    state::{ChessResult, ChessState},
+};
 
 #[derive(Properties, PartialEq)]
 pub struct SquareProp {
-    board: Board,
-    piece: Option<Piece>,
+    piece: Option<String>,
+    highlight: Highlight,
     square: (usize, usize),
     on_click: Callback<(usize, usize)>,
 }
@@ -17,20 +20,13 @@ pub struct SquareProp {
 #[function_component(ChessSquare)]
 pub fn square(
     SquareProp {
-        board,
         piece,
+        highlight,
         square,
         on_click,
     }: &SquareProp,
 ) -> Html {
     let idx = point_to_index(*square);
-    let status = if board.take_squares.contains(square) {
-        "square-take"
-    } else if board.move_squares.contains(square) {
-        "square-move"
-    } else {
-        ""
-    };
 
     let color = if (square.0 + square.1) % 2 == 0 {
         "square-black"
@@ -38,12 +34,6 @@ pub fn square(
         "square-white"
     };
 
-    let piece_name = if let Some(piece) = piece {
-        piece.class_name()
-    } else {
-        "".to_string()
-    };
-
     let on_square_click = {
         let square = *square;
         let on_click = on_click.clone();
@@ -53,13 +43,13 @@ pub fn square(
     html! {
         <div key={idx} class={classes!("chess-square", color)} onclick={on_square_click}>
             {
-                if piece.is_some() {
+                if let Some(piece_name) = piece {
                     html! { <img draggable={ "false" } class={classes!("piece")} src={ format!("assets/{piece_name}.png") }/> } }
                 else {
                     html! { }
                 }
             }
-            <div class={classes!("movement", status)}></div>
+            <div class={classes!("movement", highlight.class())}></div>
         </div>
     }
 }
@@ -96,26 +86,38 @@ pub fn chess() -> Html {
         move |_| on_start_click.emit(())
     };
 
+    let on_spectate_click = {
+        let chess_controller = chess_controller.clone();
+        move |_| chess_controller.set_spectating(!chess_controller.spectating())
+    };
+
     {
-        let update = {
-            let chess_controller = chess_controller.clone();
-            Callback::from(move |_: ()| {
-                chess_controller.get_chess();
-            })
-        };
-        use_effect(|| {
-            let interval = Interval::new(200, move || update.emit(()));
+        let chess_controller = chess_controller.clone();
+        use_effect_with(chess.spectating, move |&spectating| {
+            let interval = if spectating {
+                chess_controller.spectate()
+            } else {
+                chess_controller.poll()
+            };
             move || drop(interval)
         });
     }
 
-    let rows = chess.board.pieces.chunks(8).enumerate().map(|(y, pieces)| {
-        let offset = y * 8;
+    let on_flip_click = {
+        let chess_controller = chess_controller.clone();
+        move |_| chess_controller.flip()
+    };
 
-        let pieces: Html = pieces
+    let order: Vec<usize> = if chess.flipped {
+        (0..8).rev().collect()
+    } else {
+        (0..8).collect()
+    };
+    let rows = order.iter().map(|&y| {
+        let pieces: Html = order
             .iter()
-            .enumerate()
-            .map(|(x, piece)| html! {<ChessSquare board={chess.board.clone()} piece={piece.clone()} square={index_to_point(x + offset)} on_click={on_square_click.clone()} />}).collect();
+            .map(|&x| (x, y))
+            .map(|square| html! {<ChessSquare piece={chess.board.piece_at(square)} highlight={chess.board.highlight_at(square)} square={square} on_click={on_square_click.clone()} />}).collect();
         html! {
             <div key={y} class="chess-row">
                 { pieces }
@@ -123,12 +125,56 @@ pub fn chess() -> Html {
         }
     });
 
-    let chess_text = match chess_controller.winner() {
-        Some(Color::White) => "White Wins",
-        Some(Color::Black) => "Black Wins",
+    let promotion = match chess_controller
+        .pending_promotion()
+        .and_then(|pos| chess.board.get(&pos).clone())
+    {
+        Some(pawn) => {
+            let options = match pawn.color() {
+                Color::White => [
+                    Piece::Queen(Queen::white()),
+                    Piece::Rook(Rook::white()),
+                    Piece::Bishop(Bishop::white()),
+                    Piece::Knight(Knight::white()),
+                ],
+                Color::Black => [
+                    Piece::Queen(Queen::black()),
+                    Piece::Rook(Rook::black()),
+                    Piece::Bishop(Bishop::black()),
+                    Piece::Knight(Knight::black()),
+                ],
+            };
+            let options = options.into_iter().map(|piece| {
+                let piece_name = piece.class_name();
+                let on_click = {
+                    let chess_controller = chess_controller.clone();
+                    move |_| chess_controller.promote(piece.clone())
+                };
+                html! { <img draggable={ "false" } class="promotion-piece" src={ format!("assets/{piece_name}.png") } onclick={on_click}/> }
+            });
+            html! {
+                <div class="promotion">
+                    { for options }
+                </div>
+            }
+        }
+        None => html! {},
+    };
+
+    let chess_text = match chess_controller.result() {
+        Some(ChessResult::Winner(Color::White)) => "White Wins",
+        Some(ChessResult::Winner(Color::Black)) => "Black Wins",
+        Some(ChessResult::StaleMate) => "Draw by Stalemate",
+        Some(ChessResult::Repetition) => "Draw by Repetition",
+        Some(ChessResult::ForcedDraw) => "Draw",
         None => "Chess",
     };
 
+    let pgn = match (chess_controller.result(), chess_controller.pgn()) {
+        (Some(_), Some(pgn)) => html! { <pre class="pgn">{ pgn }</pre> },
+        _ => html! {},
+    };
+
     html! {
         <div>
             <section class="chess-container">
@@ -139,8 +185,12 @@ pub fn chess() -> Html {
                     <div class="chess">
                         { for rows }
                     </div>
+                    { promotion }
+                    { pgn }
                     <div>
                         <button onclick={on_button_click}>{ "Restart" }</button>
+                        <button onclick={on_flip_click}>{ "Flip" }</button>
+                        <button onclick={on_spectate_click}>{ if chess.spectating { "Play" } else { "Spectate" } }</button>
                     </div>
                 </section>
             </section>
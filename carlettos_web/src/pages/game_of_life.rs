@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use gloo::timers::callback::Interval;
 use rand::Rng;
 use yew::{html::Scope, prelude::*};
@@ -11,18 +13,55 @@ pub enum CellMsg {
     Reset,
     Stop,
     ToggleCell(usize),
+    SwitchBackend,
     Tick,
 }
 
+/// How the next generation is computed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Checks every cell of the board.
+    #[default]
+    Dense,
+    /// Only checks the live cells and their neighbors, faster for big boards with little life.
+    Sparse,
+}
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, 0),
+    (-1, 1),
+    (-1, -1),
+    (0, -1),
+    (0, 1),
+];
+
 pub struct GameOfLife {
     active: bool,
     cells: Vec<Cell>,
+    backend: Backend,
+    // (row, col) of the live cells, only kept with the sparse backend.
+    live: HashSet<(i32, i32)>,
     cell_width: usize,
     cell_height: usize,
-    _interval: Interval,
+    _interval: Option<Interval>,
 }
 
 impl GameOfLife {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            active: false,
+            cells: vec![Cell::new_dead(); width * height],
+            backend: Backend::Dense,
+            live: HashSet::new(),
+            cell_width: width,
+            cell_height: height,
+            _interval: None,
+        }
+    }
+
     pub fn random_mutate(&mut self) {
         for cell in self.cells.iter_mut() {
             if rand::thread_rng().gen_bool(0.5) {
@@ -31,17 +70,95 @@ impl GameOfLife {
                 cell.set_dead()
             }
         }
+        self.sync_live();
     }
 
     pub fn reset(&mut self) {
         for cell in self.cells.iter_mut() {
             cell.set_dead();
         }
+        self.live.clear();
+    }
+
+    pub fn toggle_cell(&mut self, idx: usize) {
+        self.cells[idx].toggle();
+        self.sync_live();
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+        self.sync_live();
+    }
+
+    fn sync_live(&mut self) {
+        self.live = match self.backend {
+            Backend::Dense => HashSet::new(),
+            Backend::Sparse => self
+                .cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.is_alive())
+                .map(|(idx, _)| {
+                    (
+                        (idx / self.cell_width) as i32,
+                        (idx % self.cell_width) as i32,
+                    )
+                })
+                .collect(),
+        };
     }
 
     pub fn step(&mut self) {
+        match self.backend {
+            Backend::Dense => self.step_dense(),
+            Backend::Sparse => self.step_sparse(),
+        }
+    }
+
+    fn step_sparse(&mut self) {
+        let mut counts: HashMap<(i32, i32), u8> = HashMap::new();
+        for &(row, col) in self.live.iter() {
+            for (d_row, d_col) in NEIGHBORS {
+                let row = wrap(row as isize + d_row as isize, self.cell_height as isize);
+                let col = wrap(col as isize + d_col as isize, self.cell_width as isize);
+                *counts.entry((row as i32, col as i32)).or_default() += 1;
+            }
+        }
+        let next: HashSet<(i32, i32)> = counts
+            .into_iter()
+            .filter(|(pos, n)| *n == 3 || (*n == 2 && self.live.contains(pos)))
+            .map(|(pos, _)| pos)
+            .collect();
+
+        let to_dead: Vec<usize> = self
+            .live
+            .difference(&next)
+            .map(|&(row, col)| self.row_col_as_idx(row as isize, col as isize))
+            .collect();
+        let to_update: Vec<(usize, bool)> = next
+            .iter()
+            .map(|pos| {
+                let idx = self.row_col_as_idx(pos.0 as isize, pos.1 as isize);
+                (idx, self.live.contains(pos))
+            })
+            .collect();
+        for i in to_dead {
+            self.cells[i].set_dead();
+        }
+        for (i, survived) in to_update {
+            if survived {
+                self.cells[i].grow_older();
+            } else {
+                self.cells[i].set_alive();
+            }
+        }
+        self.live = next;
+    }
+
+    fn step_dense(&mut self) {
         let mut to_dead = Vec::new();
         let mut to_live = Vec::new();
+        let mut to_age = Vec::new();
         for row in 0..self.cell_height {
             for col in 0..self.cell_width {
                 let neighbors = self.neighbors(row as isize, col as isize);
@@ -50,6 +167,8 @@ impl GameOfLife {
                 if self.cells[current_idx].is_alive() {
                     if Cell::alone(&neighbors) || Cell::overpopulated(&neighbors) {
                         to_dead.push(current_idx);
+                    } else {
+                        to_age.push(current_idx);
                     }
                 } else if Cell::can_be_revived(&neighbors) {
                     to_live.push(current_idx);
@@ -62,6 +181,9 @@ impl GameOfLife {
         for i in to_live {
             self.cells[i].set_alive();
         }
+        for i in to_age {
+            self.cells[i].grow_older();
+        }
     }
 
     fn neighbors(&self, row: isize, col: isize) -> [Cell; 8] {
@@ -85,14 +207,14 @@ impl GameOfLife {
     }
 
     fn view_cell(&self, idx: usize, cell: &Cell, link: &Scope<Self>) -> Html {
-        let status = if cell.is_alive() {
-            "cell-live"
+        let (status, age) = if cell.is_alive() {
+            ("cell-live", Some(cell.age_class()))
         } else {
-            "cell-dead"
+            ("cell-dead", None)
         };
 
         html! {
-            <div key={idx} class={classes!("game-cell", status)} onclick={link.callback(move |_| CellMsg::ToggleCell(idx))}>
+            <div key={idx} class={classes!("game-cell", status, age)} onclick={link.callback(move |_| CellMsg::ToggleCell(idx))}>
             </div>
         }
     }
@@ -106,14 +228,9 @@ impl Component for GameOfLife {
         let callback = ctx.link().callback(|_| CellMsg::Tick);
         let interval = Interval::new(200, move || callback.emit(()));
 
-        let (w, h) = (40, 30);
-
         Self {
-            active: false,
-            cells: vec![Cell::new_dead(); w * h],
-            cell_width: w,
-            cell_height: h,
-            _interval: interval,
+            _interval: Some(interval),
+            ..Self::new(40, 30)
         }
     }
 
@@ -144,7 +261,14 @@ impl Component for GameOfLife {
                 false
             }
             CellMsg::ToggleCell(idx) => {
-                self.cells[idx].toggle();
+                self.toggle_cell(idx);
+                true
+            }
+            CellMsg::SwitchBackend => {
+                self.set_backend(match self.backend {
+                    Backend::Dense => Backend::Sparse,
+                    Backend::Sparse => Backend::Dense,
+                });
                 true
             }
             CellMsg::Tick => {
@@ -194,6 +318,7 @@ impl Component for GameOfLife {
                             <button class="game-button" onclick={ctx.link().callback(|_| CellMsg::Start)}>{ "Start" }</button>
                             <button class="game-button" onclick={ctx.link().callback(|_| CellMsg::Stop)}>{ "Stop" }</button>
                             <button class="game-button" onclick={ctx.link().callback(|_| CellMsg::Reset)}>{ "Reset" }</button>
+                            <button class="game-button" onclick={ctx.link().callback(|_| CellMsg::SwitchBackend)}>{ format!("{:?}", self.backend) }</button>
                         </div>
                     </section>
                 </section>
@@ -216,3 +341,71 @@ fn wrap(coord: isize, range: isize) -> usize {
     };
     result as usize
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cell_age() {
+        let mut game = GameOfLife::new(8, 8);
+        // A block is a still life.
+        for idx in [9, 10, 17, 18] {
+            game.cells[idx].set_alive();
+        }
+        game.step();
+        game.step();
+        assert!([9, 10, 17, 18].iter().all(|&i| game.cells[i].age == 2));
+        game.step();
+        assert!([9, 10, 17, 18].iter().all(|&i| game.cells[i].age == 3));
+
+        // A blinker has its ends reborn every generation.
+        let mut game = GameOfLife::new(8, 8);
+        for idx in [25, 26, 27] {
+            game.cells[idx].set_alive();
+        }
+        game.step();
+        assert_eq!(game.cells[26].age, 1);
+        assert!(game.cells[18].is_alive() && game.cells[18].age == 0);
+        assert!(game.cells[34].is_alive() && game.cells[34].age == 0);
+        assert!(!game.cells[25].is_alive() && game.cells[25].age == 0);
+    }
+
+    fn glider(width: usize, height: usize, backend: Backend) -> GameOfLife {
+        let mut game = GameOfLife::new(width, height);
+        for (row, col) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            game.cells[row * width + col].set_alive();
+        }
+        game.set_backend(backend);
+        game
+    }
+
+    #[test]
+    fn sparse_agrees_with_dense() {
+        let mut dense = glider(12, 10, Backend::Dense);
+        let mut sparse = glider(12, 10, Backend::Sparse);
+        for _ in 0..10 {
+            dense.step();
+            sparse.step();
+            for (d, s) in dense.cells.iter().zip(sparse.cells.iter()) {
+                assert!(d.is_alive() == s.is_alive() && d.age == s.age);
+            }
+        }
+        assert_eq!(sparse.live.len(), 5);
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --ignored"]
+    fn sparse_beats_dense_on_a_large_board() {
+        let time = |backend| {
+            let mut game = glider(1000, 1000, backend);
+            let start = std::time::Instant::now();
+            for _ in 0..100 {
+                game.step();
+            }
+            start.elapsed()
+        };
+        let (dense, sparse) = (time(Backend::Dense), time(Backend::Sparse));
+        assert!(sparse < dense, "sparse {sparse:?}, dense {dense:?}");
+    }
+}
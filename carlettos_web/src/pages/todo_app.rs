@@ -1,5 +1,6 @@
 use std::rc::Rc;
 
+use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
 use crate::components::*;
@@ -40,15 +41,57 @@ pub fn app() -> Html {
         })
     };
 
+    let on_rename_task = {
+        let tasks_controller = tasks_controller.clone();
+        Callback::from(move |(id, title): (String, String)| {
+            tasks_controller.rename_task(id, title);
+        })
+    };
+
+    let on_clear_completed = {
+        let tasks_controller = tasks_controller.clone();
+        Callback::from(move |_| {
+            tasks_controller.clear_completed();
+        })
+    };
+
+    let filter_button = |filter: TaskFilter, name: &str| {
+        let tasks_controller = tasks_controller.clone();
+        let class = (tasks.filter == filter).then_some("selected");
+        html! {
+            <button class={class} onclick={move |_| tasks_controller.set_filter(filter)}>
+                { name }
+            </button>
+        }
+    };
+
+    let on_search = {
+        let tasks_controller = tasks_controller.clone();
+        Callback::from(move |e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            tasks_controller.search(input.value());
+        })
+    };
+
     html! {
         <div class="container">
             <TaskForm on_create_task={on_create_task} />
             <h3>{ "Todo" }</h3>
+            <div class="center">
+                { filter_button(TaskFilter::All, "All") }
+                { filter_button(TaskFilter::Active, "Active") }
+                { filter_button(TaskFilter::Completed, "Completed") }
+                <input type="text" placeholder="Search" value={tasks.search.clone()} oninput={on_search} />
+                <button onclick={on_clear_completed}>
+                    { format!("Clear completed ({})", tasks.completed_count()) }
+                </button>
+            </div>
             <div>
                 <TaskList
-                    tasks={tasks.tasks.clone()}
+                    tasks={tasks.visible_tasks()}
                     on_delete_task={on_delete_task}
                     on_toggle_task={on_toggle_task}
+                    on_rename_task={on_rename_task}
                 />
             </div>
         </div>
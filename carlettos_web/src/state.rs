@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+
 use carlettos_chess::chess_controller::CChess;
-use chess_api::{Board, Color};
+use chess_api::{Board, Color, GameState, Piece};
 use yew::Reducible;
 
 use crate::{
@@ -10,34 +12,101 @@ use crate::{
 pub enum ChessAction {
     Get(Board),
     Update(Board),
+    Promote(Piece),
+    Spectate(bool),
+    Flip,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChessResult {
+    Winner(Color),
+    /// The player in turn can't move without being in check.
+    StaleMate,
+    /// The same position appeared three times.
+    Repetition,
+    /// Fivefold repetition or seventy-five moves without captures or pawn moves.
+    ForcedDraw,
 }
 
 #[derive(Default)]
 pub struct ChessState {
     pub board: Board,
-    pub winner: Option<Color>,
+    pub result: Option<ChessResult>,
+    /// A pawn waiting for the player to choose its promotion.
+    pub pending_promotion: Option<(usize, usize)>,
+    /// Read only mode, the board is only fetched and clicks are ignored.
+    pub spectating: bool,
+    /// Draws the board with black at the bottom.
+    pub flipped: bool,
+}
+
+impl ChessState {
+    /// The board to send to the server after clicking `from`, if any.
+    pub fn click(&self, from: (usize, usize)) -> Option<Board> {
+        if self.spectating {
+            return None;
+        }
+        let mut board = self.board.clone();
+        board.on_click(from);
+        Some(board)
+    }
+
+    /// The game as PGN, the result tag and the moves since the start.
+    /// None if the moves can't be replayed from the starting position.
+    pub fn pgn(&self) -> Option<String> {
+        let result = match &self.result {
+            Some(ChessResult::Winner(Color::White)) => "1-0",
+            Some(ChessResult::Winner(Color::Black)) => "0-1",
+            Some(_) => "1/2-1/2",
+            None => "*",
+        };
+        let movetext = match self.board.movetext()? {
+            moves if moves.is_empty() => result.to_string(),
+            moves => format!("{moves} {result}"),
+        };
+        Some(format!("[Result \"{result}\"]\n\n{movetext}"))
+    }
 }
 
 impl Reducible for ChessState {
     type Action = ChessAction;
 
     fn reduce(self: std::rc::Rc<Self>, action: Self::Action) -> std::rc::Rc<Self> {
+        let mut spectating = self.spectating;
+        let mut flipped = self.flipped;
         let next_chess = match action {
             ChessAction::Update(board) => board,
             ChessAction::Get(board) => board,
+            ChessAction::Promote(piece) => {
+                let mut board = self.board.clone();
+                board.promote(piece);
+                board
+            }
+            ChessAction::Spectate(spectate) => {
+                spectating = spectate;
+                self.board.clone()
+            }
+            ChessAction::Flip => {
+                flipped = !flipped;
+                self.board.clone()
+            }
         };
+        let pending_promotion = next_chess.promotion_square();
 
-        let winner = if next_chess.is_check_mate(&Color::Black) {
-            Some(Color::White)
-        } else if next_chess.is_check_mate(&Color::White) {
-            Some(Color::Black)
-        } else {
-            None
+        let result = match next_chess.game_state() {
+            GameState::Ongoing => None,
+            GameState::Checkmate(winner) => Some(ChessResult::Winner(winner)),
+            GameState::Stalemate => Some(ChessResult::StaleMate),
+            GameState::ThreefoldRepetition => Some(ChessResult::Repetition),
+            GameState::ForcedDraw => Some(ChessResult::ForcedDraw),
         };
 
         Self {
             board: next_chess,
-            winner,
+            result,
+            pending_promotion,
+            spectating,
+            flipped,
         }
         .into()
     }
@@ -47,12 +116,15 @@ pub enum CarlettosChessAction {
     Start,
     OnClick(carlettos_chess::prelude::Pos),
     DisplayClick(carlettos_chess::prelude::Pos),
+    Flip,
 }
 
 #[derive(Default, PartialEq)]
 pub struct CarlettosChessState {
     pub board: CChess,
     pub display: CChess,
+    /// Draws the board with black at the bottom.
+    pub flipped: bool,
 }
 
 impl Reducible for CarlettosChessState {
@@ -63,6 +135,7 @@ impl Reducible for CarlettosChessState {
             CarlettosChessAction::Start => Self {
                 board: CChess::cchessboard(),
                 display: CChess::default_display(),
+                flipped: self.flipped,
             },
             CarlettosChessAction::OnClick(pos) => {
                 let mut board = self.board.clone();
@@ -71,14 +144,18 @@ impl Reducible for CarlettosChessState {
                     Some(dis) => {
                         // put the selected piece into the board
                         let piece = display.board.get(&dis).unwrap().piece.clone();
-                        board.board.get_mut(&pos).unwrap().replace(piece);
+                        board.place(pos, piece);
                     }
                     None => {
                         board.click(pos);
                     }
                 }
                 display.selected = None;
-                Self { board, display }
+                Self {
+                    board,
+                    display,
+                    flipped: self.flipped,
+                }
             }
             CarlettosChessAction::DisplayClick(pos) => {
                 let mut display = self.display.clone();
@@ -86,8 +163,14 @@ impl Reducible for CarlettosChessState {
                 Self {
                     board: self.board.clone(),
                     display,
+                    flipped: self.flipped,
                 }
             }
+            CarlettosChessAction::Flip => Self {
+                board: self.board.clone(),
+                display: self.display.clone(),
+                flipped: !self.flipped,
+            },
         }
         .into()
     }
@@ -97,41 +180,104 @@ pub enum TaskAction {
     Set(Vec<Task>),
     Add(Task),
     Delete(String),
+    /// Toggles the task before the server answers.
     Toggle(String),
+    /// The server answer to one optimistic toggle, reverts it when false.
+    ToggleConfirm(String, bool),
+    /// Renames the task with the given id.
+    Rename(String, String),
+    /// Drops every completed task.
+    ClearCompleted,
+    Filter(TaskFilter),
+    Search(String),
 }
 
-#[derive(Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskFilter {
+    #[default]
+    All,
+    Active,
+    Completed,
+}
+
+impl TaskFilter {
+    pub fn accepts(&self, task: &Task) -> bool {
+        match self {
+            TaskFilter::All => true,
+            TaskFilter::Active => !task.completed,
+            TaskFilter::Completed => task.completed,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct TaskState {
     pub tasks: Vec<Task>,
+    pub filter: TaskFilter,
+    pub search: String,
+    /// Toggles sent to the server and not yet answered, counted by task id.
+    pub pending: HashMap<String, usize>,
+}
+
+impl TaskState {
+    fn toggle(&mut self, id: &str) {
+        let task = self.tasks.iter_mut().find(|task| task.id == id);
+        if let Some(task) = task {
+            task.completed = !task.completed;
+        }
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.tasks.iter().filter(|task| task.completed).count()
+    }
+
+    /// The tasks that pass the filter and whose title contains the search, ignoring case.
+    pub fn visible_tasks(&self) -> Vec<Task> {
+        let search = self.search.to_lowercase();
+        self.tasks
+            .iter()
+            .filter(|task| self.filter.accepts(task))
+            .filter(|task| task.title.to_lowercase().contains(&search))
+            .cloned()
+            .collect()
+    }
 }
 
 impl Reducible for TaskState {
     type Action = TaskAction;
 
     fn reduce(self: std::rc::Rc<Self>, action: Self::Action) -> std::rc::Rc<Self> {
-        let next_tasks = match action {
-            TaskAction::Set(tasks) => tasks,
-            TaskAction::Add(task) => {
-                let mut tasks = self.tasks.clone();
-                tasks.push(task);
-                tasks
+        let mut state = (*self).clone();
+        match action {
+            TaskAction::Set(tasks) => state.tasks = tasks,
+            TaskAction::Add(task) => state.tasks.push(task),
+            TaskAction::Delete(id) => state.tasks.retain(|task| task.id != id),
+            TaskAction::Toggle(id) => {
+                state.toggle(&id);
+                *state.pending.entry(id).or_default() += 1;
             }
-            TaskAction::Delete(id) => {
-                let mut tasks = self.tasks.clone();
-                tasks.retain(|task| task.id != id);
-                tasks
+            TaskAction::ToggleConfirm(id, confirmed) => {
+                if let Some(count) = state.pending.get_mut(&id) {
+                    *count -= 1;
+                    if *count == 0 {
+                        state.pending.remove(&id);
+                    }
+                    if !confirmed {
+                        state.toggle(&id);
+                    }
+                }
             }
-            TaskAction::Toggle(id) => {
-                let mut tasks = self.tasks.clone();
-                let task = tasks.iter_mut().find(|task| task.id == id);
+            TaskAction::Rename(id, title) => {
+                let task = state.tasks.iter_mut().find(|task| task.id == id);
                 if let Some(task) = task {
-                    task.completed = !task.completed;
+                    task.title = title;
                 }
-                tasks
             }
-        };
-
-        Self { tasks: next_tasks }.into()
+            TaskAction::ClearCompleted => state.tasks.retain(|task| !task.completed),
+            TaskAction::Filter(filter) => state.filter = filter,
+            TaskAction::Search(search) => state.search = search,
+        }
+        state.into()
     }
 }
 
@@ -183,11 +329,15 @@ impl Reducible for VotesState {
 #[derive(Default)]
 pub struct C2048LeaderboardState {
     pub entries: Vec<Entry>,
+    /// Token of the last fetch, responses of older fetches are ignored.
+    pub generation: usize,
 }
 
 pub enum C2048LeaderboardAction {
     Add(Entry),
-    Load(Vec<Entry>),
+    /// Starts a new fetch, outdating the ones in flight.
+    Fetch,
+    Load(usize, Vec<Entry>),
 }
 
 impl Reducible for C2048LeaderboardState {
@@ -195,12 +345,282 @@ impl Reducible for C2048LeaderboardState {
 
     fn reduce(self: std::rc::Rc<Self>, action: Self::Action) -> std::rc::Rc<Self> {
         match action {
-            C2048LeaderboardAction::Load(entries) => C2048LeaderboardState { entries }.into(),
+            C2048LeaderboardAction::Fetch => C2048LeaderboardState {
+                entries: self.entries.clone(),
+                generation: self.generation + 1,
+            }
+            .into(),
+            C2048LeaderboardAction::Load(generation, _) if generation != self.generation => self,
+            C2048LeaderboardAction::Load(generation, entries) => C2048LeaderboardState {
+                entries,
+                generation,
+            }
+            .into(),
             C2048LeaderboardAction::Add(entry) => {
                 let mut entries = self.entries.clone();
                 entries.push(entry);
-                C2048LeaderboardState { entries }.into()
+                C2048LeaderboardState {
+                    entries,
+                    generation: self.generation,
+                }
+                .into()
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use chess_api::{point_to_index, King, Knight, Pawn, Queen, Rook};
+
+    use super::*;
+
+    fn board_with(pieces: Vec<((usize, usize), Piece)>, turn: Color) -> Board {
+        let mut board = Board {
+            pieces: vec![None; 64],
+            turn,
+            ..Default::default()
+        };
+        for (pos, piece) in pieces {
+            board.pieces[point_to_index(pos)] = Some(piece);
+        }
+        board
+    }
+
+    #[test]
+    fn chess_result() {
+        let state = Rc::new(ChessState::default());
+        let state = state.reduce(ChessAction::Update(Board::default()));
+        assert_eq!(state.result, None);
+
+        let mate = board_with(
+            vec![
+                ((7, 0), Piece::King(King::black())),
+                ((6, 1), Piece::Pawn(Pawn::black())),
+                ((7, 1), Piece::Pawn(Pawn::black())),
+                ((0, 0), Piece::Rook(Rook::white())),
+                ((4, 7), Piece::King(King::white())),
+            ],
+            Color::Black,
+        );
+        let state = state.reduce(ChessAction::Update(mate));
+        assert_eq!(state.result, Some(ChessResult::Winner(Color::White)));
+
+        let stale = board_with(
+            vec![
+                ((7, 0), Piece::King(King::black())),
+                ((5, 1), Piece::Queen(Queen::white())),
+                ((0, 7), Piece::King(King::white())),
+            ],
+            Color::Black,
+        );
+        let state = state.reduce(ChessAction::Update(stale));
+        assert_eq!(state.result, Some(ChessResult::StaleMate));
+    }
+
+    #[test]
+    fn pgn_export() {
+        let state = Rc::new(ChessState::default());
+        assert_eq!(state.pgn().unwrap(), "[Result \"*\"]\n\n*");
+
+        let mut board = Board::default();
+        for (from, to) in [
+            ((2, 6), (2, 5)), // f3
+            ((3, 1), (3, 2)), // e6
+            ((1, 6), (1, 4)), // g4
+            ((4, 0), (0, 4)), // Qh4#
+        ] {
+            board.apply_move(from, to, None).unwrap();
+        }
+        let state = state.reduce(ChessAction::Update(board));
+        assert_eq!(state.result, Some(ChessResult::Winner(Color::Black)));
+        assert_eq!(
+            state.pgn().unwrap(),
+            "[Result \"0-1\"]\n\n1. f3 e6 2. g4 Qh4# 0-1"
+        );
+    }
+
+    #[test]
+    fn promotion() {
+        let mut board = board_with(
+            vec![
+                ((0, 1), Piece::Pawn(Pawn::white())),
+                ((4, 7), Piece::King(King::white())),
+                ((7, 3), Piece::King(King::black())),
+            ],
+            Color::White,
+        );
+        board.on_click((0, 1));
+        board.on_click((0, 0));
+
+        let state = Rc::new(ChessState::default());
+        let state = state.reduce(ChessAction::Update(board));
+        assert_eq!(state.pending_promotion, Some((0, 0)));
+
+        let state = state.reduce(ChessAction::Promote(Piece::Knight(Knight::white())));
+        assert_eq!(state.pending_promotion, None);
+        assert_eq!(
+            state.board.get(&(0, 0)),
+            &Some(Piece::Knight(Knight::white()))
+        );
+    }
+
+    #[test]
+    fn spectating_ignores_clicks() {
+        let state = Rc::new(ChessState::default());
+        let state = state.reduce(ChessAction::Get(Board::default()));
+        let clicked = state.click((0, 6)).unwrap();
+        assert_eq!(clicked.piece_selected, Some((0, 6)));
+
+        let state = state.reduce(ChessAction::Spectate(true));
+        assert!(state.click((0, 6)).is_none());
+        let state = state.reduce(ChessAction::Get(Board::default()));
+        assert!(state.spectating);
+        assert!(state.click((0, 6)).is_none());
+
+        let state = state.reduce(ChessAction::Spectate(false));
+        assert!(state.click((0, 6)).is_some());
+    }
+
+    #[test]
+    fn stale_highscores_are_dropped() {
+        let entry = |name: &str| Entry::new(name.to_string(), 4, 2, 0, 0);
+        let state = Rc::new(C2048LeaderboardState::default());
+        let state = state.reduce(C2048LeaderboardAction::Fetch);
+        let stale = state.generation;
+        let state = state.reduce(C2048LeaderboardAction::Fetch);
+        let current = state.generation;
+
+        let state = state.reduce(C2048LeaderboardAction::Load(stale, vec![entry("old")]));
+        assert!(state.entries.is_empty());
+        let state = state.reduce(C2048LeaderboardAction::Load(current, vec![entry("new")]));
+        assert_eq!(state.entries.len(), 1);
+        assert_eq!(state.entries[0].name, "new");
+    }
+
+    fn task(id: &str, title: &str) -> Task {
+        Task {
+            id: id.to_string(),
+            title: title.to_string(),
+            completed: false,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn rename_task() {
+        let state = Rc::new(TaskState::default());
+        let state = state.reduce(TaskAction::Set(vec![task("a", "milk"), task("b", "eggs")]));
+        let state = state.reduce(TaskAction::Rename("b".to_string(), "bread".to_string()));
+        assert_eq!(state.tasks[0].title, "milk");
+        assert_eq!(state.tasks[1].title, "bread");
+        assert_eq!(state.tasks[1].id, "b");
+    }
+
+    #[test]
+    fn delete_task_by_id() {
+        let state = Rc::new(TaskState::default());
+        let tasks = vec![task("a", "milk"), task("b", "eggs"), task("c", "bread")];
+        let state = state.reduce(TaskAction::Set(tasks));
+        let state = state.reduce(TaskAction::Delete("b".to_string()));
+        let ids: Vec<_> = state.tasks.iter().map(|task| task.id.as_str()).collect();
+        assert_eq!(ids, ["a", "c"]);
+
+        let state = state.reduce(TaskAction::Toggle("c".to_string()));
+        assert!(!state.tasks[0].completed);
+        assert!(state.tasks[1].completed);
+    }
+
+    #[test]
+    fn filter_and_search_tasks() {
+        let state = Rc::new(TaskState::default());
+        let tasks = vec![
+            task("a", "Buy milk"),
+            task("b", "Walk dog"),
+            task("c", "Buy eggs"),
+        ];
+        let state = state.reduce(TaskAction::Set(tasks));
+        let state = state.reduce(TaskAction::Toggle("c".to_string()));
+
+        let state = state.reduce(TaskAction::Filter(TaskFilter::Active));
+        let ids: Vec<_> = state
+            .visible_tasks()
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, ["a", "b"]);
+
+        let state = state.reduce(TaskAction::Filter(TaskFilter::All));
+        let state = state.reduce(TaskAction::Search("bUY".to_string()));
+        let ids: Vec<_> = state
+            .visible_tasks()
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, ["a", "c"]);
+
+        let state = state.reduce(TaskAction::Filter(TaskFilter::Completed));
+        let ids: Vec<_> = state
+            .visible_tasks()
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, ["c"]);
+    }
+
+    #[test]
+    fn clear_completed_tasks() {
+        let state = Rc::new(TaskState::default());
+        let tasks = vec![task("a", "milk"), task("b", "eggs"), task("c", "bread")];
+        let state = state.reduce(TaskAction::Set(tasks));
+        let state = state.reduce(TaskAction::Toggle("a".to_string()));
+        let state = state.reduce(TaskAction::Toggle("c".to_string()));
+        assert_eq!(state.completed_count(), 2);
+
+        let state = state.reduce(TaskAction::ClearCompleted);
+        assert_eq!(state.completed_count(), 0);
+        let ids: Vec<_> = state.tasks.iter().map(|task| task.id.as_str()).collect();
+        assert_eq!(ids, ["b"]);
+    }
+
+    #[test]
+    fn optimistic_toggle() {
+        let state = Rc::new(TaskState::default());
+        let state = state.reduce(TaskAction::Set(vec![task("a", "milk"), task("b", "eggs")]));
+
+        let state = state.reduce(TaskAction::Toggle("a".to_string()));
+        assert!(state.tasks[0].completed);
+        let state = state.reduce(TaskAction::ToggleConfirm("a".to_string(), true));
+        assert!(state.tasks[0].completed);
+        assert!(state.pending.is_empty());
+
+        let state = state.reduce(TaskAction::Toggle("b".to_string()));
+        assert!(state.tasks[1].completed);
+        let state = state.reduce(TaskAction::ToggleConfirm("b".to_string(), false));
+        assert!(!state.tasks[1].completed);
+        assert!(state.tasks[0].completed);
+
+        // an answer for a toggle that is not pending does nothing
+        let state = state.reduce(TaskAction::ToggleConfirm("a".to_string(), false));
+        assert!(state.tasks[0].completed);
+    }
+
+    #[test]
+    fn overlapping_toggles_roll_back_one_each() {
+        let state = Rc::new(TaskState::default());
+        let state = state.reduce(TaskAction::Set(vec![task("a", "milk")]));
+
+        let state = state.reduce(TaskAction::Toggle("a".to_string()));
+        let state = state.reduce(TaskAction::Toggle("a".to_string()));
+        assert!(!state.tasks[0].completed);
+        assert_eq!(state.pending["a"], 2);
+
+        let state = state.reduce(TaskAction::ToggleConfirm("a".to_string(), true));
+        assert!(!state.tasks[0].completed);
+        let state = state.reduce(TaskAction::ToggleConfirm("a".to_string(), false));
+        assert!(state.tasks[0].completed);
+        assert!(state.pending.is_empty());
+    }
+}
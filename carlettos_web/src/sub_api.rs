@@ -60,6 +60,17 @@ pub async fn toggle_task(id: String) -> Result<AffectedRows, Error> {
         .await
 }
 
+pub async fn rename_task(id: String, title: &str) -> Result<AffectedRows, Error> {
+    // the title is a path segment, a slash or a question mark in it would break the route
+    let title = js_sys::encode_uri_component(title);
+    Request::patch(&format!("{}/task/{id}/title/{title}", *API_IP))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+}
+
 pub async fn delete_task(id: String) -> Result<AffectedRows, Error> {
     Request::delete(&format!("{}/task/{id}", *API_IP))
         .send()
@@ -69,6 +80,15 @@ pub async fn delete_task(id: String) -> Result<AffectedRows, Error> {
         .await
 }
 
+pub async fn clear_completed() -> Result<AffectedRows, Error> {
+    Request::delete(&format!("{}/tasks/completed", *API_IP))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+}
+
 pub async fn get_votes(id: String) -> Result<Votes, Error> {
     Request::get(&format!("{}/votes/{id}", *API_IP))
         .send()
@@ -0,0 +1,807 @@
+//! A small alpha-beta player, which starts from an opening book when it knows the position.
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use crate::{point_to_index, Board, Color, Move, Piece};
+
+/// Score of being checkmated, faster mates score further from zero.
+const MATE: i32 = 100_000;
+
+/// Lines played from the starting position, used to build the default `OpeningBook`.
+/// When two lines share a position, the first one decides the move.
+const BOOK_LINES: &[&[Move]] = &[
+    &[
+        ((4, 6), (4, 4)),
+        ((4, 1), (4, 3)),
+        ((6, 7), (5, 5)),
+        ((1, 0), (2, 2)),
+    ],
+    &[
+        ((3, 6), (3, 4)),
+        ((3, 1), (3, 3)),
+        ((1, 7), (2, 5)),
+        ((6, 0), (5, 2)),
+    ],
+    &[((6, 7), (5, 5)), ((6, 0), (5, 2)), ((3, 6), (3, 5))],
+];
+
+/// Recommended moves by position, see `Board::position_hash`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    moves: HashMap<u64, Move>,
+}
+
+impl OpeningBook {
+    pub fn new(moves: HashMap<u64, Move>) -> Self {
+        Self { moves }
+    }
+
+    /// Plays every line from the starting position, recording the move made in each position.
+    pub fn from_lines(lines: &[&[Move]]) -> Self {
+        let mut moves = HashMap::new();
+        for line in lines {
+            let mut board = Board::default();
+            for mv in line.iter() {
+                moves.entry(board.position_hash()).or_insert(*mv);
+                board = board
+                    .after_move(*mv)
+                    .expect("the opening book has an illegal move");
+            }
+        }
+        Self { moves }
+    }
+
+    pub fn get(&self, board: &Board) -> Option<Move> {
+        self.moves.get(&board.position_hash()).copied()
+    }
+}
+
+/// Zobrist keys: one per piece kind, color and square, then the side to move, the four
+/// castling rights and the eight en passant files.
+const ZOBRIST_KEYS: [u64; 781] = zobrist_keys(0x5eed_c4e5_5000_0001);
+
+const SIDE_KEY: usize = 768;
+const CASTLING_KEYS: usize = 769;
+const EN_PASSANT_KEYS: usize = 773;
+
+/// Fills the keys with splitmix64, so they are the same on every build.
+const fn zobrist_keys(mut seed: u64) -> [u64; 781] {
+    let mut keys = [0; 781];
+    let mut i = 0;
+    while i < keys.len() {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+fn zobrist_piece(piece: &Piece, index: usize) -> u64 {
+    let kind = match piece {
+        Piece::Pawn(_) => 0,
+        Piece::Knight(_) => 1,
+        Piece::Bishop(_) => 2,
+        Piece::Rook(_) => 3,
+        Piece::Queen(_) => 4,
+        Piece::King(_) => 5,
+    };
+    let color = match piece.color() {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    ZOBRIST_KEYS[(kind * 2 + color) * 64 + index]
+}
+
+fn default_book() -> &'static OpeningBook {
+    static BOOK: OnceLock<OpeningBook> = OnceLock::new();
+    BOOK.get_or_init(|| OpeningBook::from_lines(BOOK_LINES))
+}
+
+fn piece_value(piece: &Piece) -> i32 {
+    match piece {
+        Piece::Pawn(_) => 100,
+        Piece::Knight(_) => 320,
+        Piece::Bishop(_) => 330,
+        Piece::Rook(_) => 500,
+        Piece::Queen(_) => 900,
+        Piece::King(_) => 0,
+    }
+}
+
+/// Like `piece_value`, but kings are worth more than anything so they take last.
+fn exchange_value(piece: &Piece) -> i32 {
+    match piece {
+        Piece::King(_) => MATE,
+        piece => piece_value(piece),
+    }
+}
+
+impl Board {
+    /// Hash of the pieces, the turn, the en passant square and the castling rights,
+    /// the selection state is ignored. The same as `Board::zobrist_hash`.
+    pub fn position_hash(&self) -> u64 {
+        self.zobrist_hash()
+    }
+
+    /// Zobrist hash of the position, with fixed keys so it's the same across runs and builds.
+    /// Boards with the same pieces, turn, castling rights and en passant file hash equal,
+    /// however they were reached.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = self
+            .pieces
+            .iter()
+            .enumerate()
+            .filter_map(|(index, piece)| piece.as_ref().map(|piece| zobrist_piece(piece, index)))
+            .fold(0, |hash, key| hash ^ key);
+        if self.turn == Color::Black {
+            hash ^= ZOBRIST_KEYS[SIDE_KEY];
+        }
+        for right in 0..4 {
+            if self.castling_rights & (1 << right) != 0 {
+                hash ^= ZOBRIST_KEYS[CASTLING_KEYS + right];
+            }
+        }
+        if let Some((file, _)) = self.en_passant_square {
+            hash ^= ZOBRIST_KEYS[EN_PASSANT_KEYS + file];
+        }
+        hash
+    }
+
+    /// Material balance in centipawns from the point of view of `color`.
+    pub fn evaluate(&self, color: &Color) -> i32 {
+        self.pieces
+            .iter()
+            .flatten()
+            .map(|piece| match piece.color() == color {
+                true => piece_value(piece),
+                false => -piece_value(piece),
+            })
+            .sum()
+    }
+
+    /// Material won by the side capturing from `from` on `to` if both sides keep recapturing with
+    /// their least valuable attacker, and stop as soon as it stops paying off.
+    pub fn see(&self, from: (usize, usize), to: (usize, usize)) -> i32 {
+        let Some(attacker) = self.get(&from) else {
+            return 0;
+        };
+        let mut board = self.clone();
+        let mut gains = vec![self.get(&to).as_ref().map_or(0, piece_value)];
+        let mut on_square = exchange_value(attacker);
+        let mut color = attacker.color().other();
+        board.pieces[point_to_index(to)] = board.pieces[point_to_index(from)].take();
+        // pieces behind the ones that took join the exchange, as the board is updated
+        while let Some(next) = board
+            .attackers_of(to, &color)
+            .into_iter()
+            .min_by_key(|pos| board.get(pos).as_ref().map_or(0, exchange_value))
+        {
+            gains.push(on_square - gains[gains.len() - 1]);
+            on_square = board.get(&next).as_ref().map_or(0, exchange_value);
+            board.pieces[point_to_index(to)] = board.pieces[point_to_index(next)].take();
+            color = color.other();
+        }
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+        gains[0]
+    }
+
+    /// The board after making the move, with pawns reaching the last rank promoted to queens.
+    /// None if the move is not legal for the piece in `from`.
+    pub fn after_move(&self, (from, to): Move) -> Option<Board> {
+        let mut board = self.clone();
+        board.turn = self.get(&from).as_ref()?.color().clone();
+        board.apply_move(from, to, None).ok()?;
+        Some(board)
+    }
+
+    /// The book move if the position is in the default opening book, otherwise searches `depth` plies.
+    pub fn best_move(&self, color: &Color, depth: usize) -> Option<Move> {
+        self.best_move_with_book(color, depth, default_book())
+    }
+
+    pub fn best_move_with_book(
+        &self,
+        color: &Color,
+        depth: usize,
+        book: &OpeningBook,
+    ) -> Option<Move> {
+        let moves = self.legal_moves(color);
+        if let Some(((fx, fy), (tx, ty))) = book.get(self) {
+            if &self.turn == color && moves.contains(&(fx, fy, tx, ty)) {
+                return Some(((fx, fy), (tx, ty)));
+            }
+        }
+
+        Searcher::default().best_move(self, color, depth)
+    }
+
+    /// Deepens the search for the side to move until `max` has passed, see `Searcher::best_move_timed`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn best_move_timed(&self, max: Duration) -> Option<Move> {
+        Searcher::with_table().best_move_timed(self, &self.turn, &InstantClock::new(), max)
+    }
+}
+
+/// Source of time for the timed search, `std::time::Instant` panics on `wasm32-unknown-unknown`
+/// so the browser has to provide its own.
+pub trait Clock {
+    /// Time since an arbitrary fixed origin.
+    fn now(&self) -> Duration;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+pub struct InstantClock {
+    origin: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl InstantClock {
+    pub fn new() -> Self {
+        Self {
+            origin: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for InstantClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for InstantClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeType {
+    /// The score is the exact value of the position.
+    Exact,
+    /// The search failed high, the position is worth at least the score.
+    LowerBound,
+    /// The search failed low, the position is worth at most the score.
+    UpperBound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TableEntry {
+    depth: usize,
+    score: i32,
+    node_type: NodeType,
+    age: u32,
+}
+
+/// Scores of already searched positions, keyed by `Board::position_hash`.
+#[derive(Debug, Clone, Default)]
+pub struct TranspositionTable {
+    entries: HashMap<u64, TableEntry>,
+    age: u32,
+}
+
+impl TranspositionTable {
+    /// Searches older than this many moves are forgotten.
+    const MAX_AGE: u32 = 2;
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, hash: u64) -> Option<(usize, i32, NodeType)> {
+        self.entries
+            .get(&hash)
+            .map(|entry| (entry.depth, entry.score, entry.node_type))
+    }
+
+    /// Keeps the deepest search of the position, unless the stored one is from an older move.
+    pub fn insert(&mut self, hash: u64, depth: usize, score: i32, node_type: NodeType) {
+        let age = self.age;
+        let entry = TableEntry {
+            depth,
+            score,
+            node_type,
+            age,
+        };
+        self.entries
+            .entry(hash)
+            .and_modify(|old| {
+                if old.age != age || old.depth <= depth {
+                    *old = entry;
+                }
+            })
+            .or_insert(entry);
+    }
+
+    /// Called between moves, drops the entries that are too old to be useful.
+    pub fn age(&mut self) {
+        self.age += 1;
+        let age = self.age;
+        self.entries
+            .retain(|_, entry| age - entry.age <= Self::MAX_AGE);
+    }
+}
+
+/// Alpha-beta search counting the visited nodes, optionally reusing a `TranspositionTable`.
+#[derive(Debug, Clone)]
+pub struct Searcher {
+    pub nodes: u64,
+    pub table: Option<TranspositionTable>,
+    /// Deepest search completed by the last call to `best_move_timed`.
+    pub depth: usize,
+    /// Searches captures first, most valuable victim by least valuable attacker, then killer moves.
+    pub ordering: bool,
+    /// Quiet moves that caused a cutoff, by remaining depth.
+    killers: Vec<[Option<Move>; 2]>,
+    aborted: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self {
+            nodes: 0,
+            table: None,
+            depth: 0,
+            ordering: true,
+            killers: Vec::new(),
+            aborted: false,
+        }
+    }
+}
+
+impl Searcher {
+    pub fn with_table() -> Self {
+        Self {
+            table: Some(TranspositionTable::default()),
+            ..Default::default()
+        }
+    }
+
+    /// The best move for `color` searching `depth` plies, the table is aged before searching.
+    pub fn best_move(&mut self, board: &Board, color: &Color, depth: usize) -> Option<Move> {
+        if let Some(table) = &mut self.table {
+            table.age();
+        }
+        self.search(board, color, depth, &|| false)
+    }
+
+    /// Searches one ply deeper each time until `max` has passed on the `clock`, returning the
+    /// best move of the deepest completed search. An unfinished search is thrown away, unless not
+    /// even the first ply finished, then the best move found so far is used.
+    pub fn best_move_timed(
+        &mut self,
+        board: &Board,
+        color: &Color,
+        clock: &dyn Clock,
+        max: Duration,
+    ) -> Option<Move> {
+        if let Some(table) = &mut self.table {
+            table.age();
+        }
+        let start = clock.now();
+        let stop = || clock.now().saturating_sub(start) >= max;
+        let mut best = None;
+        self.depth = 0;
+        loop {
+            let depth = self.depth + 1;
+            let found = self.search(board, color, depth, &stop);
+            if self.aborted {
+                return best.or(found);
+            }
+            best = found;
+            self.depth = depth;
+            // nothing to deepen without moves, or with a single reply
+            if best.is_none() || board.legal_moves(color).len() == 1 || stop() {
+                return best;
+            }
+        }
+    }
+
+    fn search(
+        &mut self,
+        board: &Board,
+        color: &Color,
+        depth: usize,
+        stop: &dyn Fn() -> bool,
+    ) -> Option<Move> {
+        self.aborted = false;
+        let mut best = None;
+        let mut alpha = -MATE * 2;
+        for (fx, fy, tx, ty) in self.ordered_moves(board, color, depth) {
+            let mv = ((fx, fy), (tx, ty));
+            let Some(after) = board.after_move(mv) else {
+                continue;
+            };
+            let score = -self.alpha_beta(
+                &after,
+                &color.other(),
+                depth.saturating_sub(1),
+                -MATE * 2,
+                -alpha,
+                stop,
+            );
+            if self.aborted {
+                break;
+            }
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some(mv);
+            }
+        }
+        best
+    }
+
+    fn ordered_moves(
+        &self,
+        board: &Board,
+        color: &Color,
+        depth: usize,
+    ) -> Vec<(usize, usize, usize, usize)> {
+        let mut moves = board.legal_moves(color);
+        if !self.ordering {
+            return moves;
+        }
+        let killers = self.killers.get(depth).copied().unwrap_or_default();
+        moves.sort_by_cached_key(|&(fx, fy, tx, ty)| {
+            let attacker = board.get(&(fx, fy)).as_ref().map_or(0, exchange_value);
+            match board.get(&(tx, ty)) {
+                Some(victim) => (0, -piece_value(victim), attacker),
+                None if killers.contains(&Some(((fx, fy), (tx, ty)))) => (1, 0, 0),
+                None => (2, 0, 0),
+            }
+        });
+        moves
+    }
+
+    fn add_killer(&mut self, depth: usize, mv: Move) {
+        if self.killers.len() <= depth {
+            self.killers.resize(depth + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[depth];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+    }
+
+    fn alpha_beta(
+        &mut self,
+        board: &Board,
+        color: &Color,
+        depth: usize,
+        mut alpha: i32,
+        mut beta: i32,
+        stop: &dyn Fn() -> bool,
+    ) -> i32 {
+        if self.aborted || stop() {
+            self.aborted = true;
+            return 0;
+        }
+        self.nodes += 1;
+        if depth == 0 {
+            return board.evaluate(color);
+        }
+
+        let hash = board.position_hash();
+        let original_alpha = alpha;
+        if let Some((entry_depth, score, node_type)) =
+            self.table.as_ref().and_then(|table| table.get(hash))
+        {
+            if entry_depth >= depth {
+                match node_type {
+                    NodeType::Exact => return score,
+                    NodeType::LowerBound => alpha = alpha.max(score),
+                    NodeType::UpperBound => beta = beta.min(score),
+                }
+                if alpha >= beta {
+                    return score;
+                }
+            }
+        }
+
+        let moves = self.ordered_moves(board, color, depth);
+        if moves.is_empty() {
+            return match board.is_check(color) {
+                true => -MATE - depth as i32,
+                false => 0,
+            };
+        }
+        let mut best = -MATE * 2;
+        for (fx, fy, tx, ty) in moves {
+            let Some(after) = board.after_move(((fx, fy), (tx, ty))) else {
+                continue;
+            };
+            let score = -self.alpha_beta(&after, &color.other(), depth - 1, -beta, -alpha, stop);
+            if self.aborted {
+                return 0;
+            }
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                if board.get(&(tx, ty)).is_none() {
+                    self.add_killer(depth, ((fx, fy), (tx, ty)));
+                }
+                break;
+            }
+        }
+
+        if let Some(table) = &mut self.table {
+            let node_type = if best <= original_alpha {
+                NodeType::UpperBound
+            } else if best >= beta {
+                NodeType::LowerBound
+            } else {
+                NodeType::Exact
+            };
+            table.insert(hash, depth, best, node_type);
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test::board_with, Bishop, King, Knight, Pawn, Queen, Rook};
+
+    #[test]
+    fn material_balance() {
+        let board = Board::default();
+        assert_eq!(board.evaluate(&Color::White), 0);
+
+        let mut board = board;
+        board.pieces[point_to_index((0, 0))] = None;
+        assert_eq!(board.evaluate(&Color::White), 500);
+        assert_eq!(board.evaluate(&Color::Black), -500);
+
+        let board = board_with(&[
+            ((3, 7), Piece::King(King::white())),
+            ((3, 0), Piece::King(King::black())),
+            ((0, 6), Piece::Pawn(Pawn::white())),
+            ((1, 1), Piece::Knight(Knight::black())),
+            ((2, 2), Piece::Bishop(Bishop::black())),
+        ]);
+        assert_eq!(board.evaluate(&Color::Black), 320 + 330 - 100);
+    }
+
+    #[test]
+    fn static_exchange_evaluation() {
+        let defended_pawn = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((3, 7), Piece::Queen(Queen::white())),
+            ((0, 0), Piece::King(King::black())),
+            ((3, 3), Piece::Pawn(Pawn::black())),
+            ((4, 2), Piece::Pawn(Pawn::black())),
+        ]);
+        assert_eq!(defended_pawn.see((3, 7), (3, 3)), 100 - 900);
+
+        let hanging_knight = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((3, 7), Piece::Rook(Rook::white())),
+            ((0, 0), Piece::King(King::black())),
+            ((3, 3), Piece::Knight(Knight::black())),
+        ]);
+        assert_eq!(hanging_knight.see((3, 7), (3, 3)), 320);
+
+        // the rook behind joins in, so the defended knight is still won
+        let battery = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((3, 7), Piece::Rook(Rook::white())),
+            ((3, 6), Piece::Rook(Rook::white())),
+            ((0, 0), Piece::King(King::black())),
+            ((3, 3), Piece::Knight(Knight::black())),
+            ((3, 0), Piece::Rook(Rook::black())),
+        ]);
+        assert_eq!(battery.see((3, 6), (3, 3)), 320);
+    }
+
+    #[test]
+    fn transpositions_hash_equal() {
+        let play = |moves: &[Move]| {
+            moves
+                .iter()
+                .fold(Board::default(), |board, mv| board.after_move(*mv).unwrap())
+        };
+        let first = play(&[((1, 7), (2, 5)), ((1, 0), (2, 2)), ((6, 7), (5, 5))]);
+        let second = play(&[((6, 7), (5, 5)), ((1, 0), (2, 2)), ((1, 7), (2, 5))]);
+        assert_eq!(first.zobrist_hash(), second.zobrist_hash());
+        assert_eq!(first.position_hash(), first.zobrist_hash());
+
+        let start = Board::default();
+        assert_ne!(start.zobrist_hash(), first.zobrist_hash());
+        let mut black_to_move = start.clone();
+        black_to_move.turn = Color::Black;
+        assert_ne!(start.zobrist_hash(), black_to_move.zobrist_hash());
+        let mut no_castling = start.clone();
+        no_castling.castling_rights = 0;
+        assert_ne!(start.zobrist_hash(), no_castling.zobrist_hash());
+        let mut en_passant = start.clone();
+        en_passant.en_passant_square = Some((3, 5));
+        assert_ne!(start.zobrist_hash(), en_passant.zobrist_hash());
+    }
+
+    #[test]
+    fn book_moves() {
+        let board = Board::default();
+        let mv = board.best_move(&Color::White, 2);
+        assert_eq!(mv, Some(((4, 6), (4, 4))));
+
+        let board = board.after_move(mv.unwrap()).unwrap();
+        assert_eq!(board.best_move(&Color::Black, 2), Some(((4, 1), (4, 3))));
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let board = board_with(&[
+            ((3, 7), Piece::King(King::white())),
+            ((7, 5), Piece::Rook(Rook::white())),
+            ((3, 0), Piece::King(King::black())),
+            ((2, 1), Piece::Pawn(Pawn::black())),
+            ((3, 1), Piece::Pawn(Pawn::black())),
+            ((4, 1), Piece::Pawn(Pawn::black())),
+        ]);
+        for depth in 1..=3 {
+            assert_eq!(
+                board.best_move(&Color::White, depth),
+                Some(((7, 5), (7, 0)))
+            );
+        }
+
+        let mated = board.after_move(((7, 5), (7, 0))).unwrap();
+        assert!(mated.is_check_mate(&Color::Black));
+        assert_eq!(mated.best_move(&Color::Black, 3), None);
+
+        let mut stalemate = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((5, 1), Piece::Queen(Queen::white())),
+            ((7, 0), Piece::King(King::black())),
+        ]);
+        stalemate.turn = Color::Black;
+        assert!(stalemate.is_stalemate(&Color::Black));
+        assert_eq!(stalemate.best_move(&Color::Black, 3), None);
+    }
+
+    #[test]
+    fn unknown_positions_are_searched() {
+        let board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((4, 4), Piece::Queen(Queen::white())),
+            ((0, 0), Piece::King(King::black())),
+            ((7, 4), Piece::Rook(Rook::black())),
+        ]);
+        assert_eq!(default_book().get(&board), None);
+        assert_eq!(board.best_move(&Color::White, 2), Some(((4, 4), (7, 4))));
+
+        let empty = OpeningBook::default();
+        let board = Board::default();
+        let (from, to) = board.best_move_with_book(&Color::White, 1, &empty).unwrap();
+        assert!(board
+            .legal_moves(&Color::White)
+            .contains(&(from.0, from.1, to.0, to.1)));
+    }
+
+    #[test]
+    fn transposition_table_saves_nodes() {
+        let board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((0, 7), Piece::Rook(Rook::white())),
+            ((3, 0), Piece::King(King::black())),
+        ]);
+
+        let mut plain = Searcher::default();
+        let mut cached = Searcher::with_table();
+        let plain_move = plain.best_move(&board, &Color::White, 4);
+        let cached_move = cached.best_move(&board, &Color::White, 4);
+        assert_eq!(plain_move, cached_move);
+        assert!(
+            cached.nodes < plain.nodes,
+            "{} {}",
+            cached.nodes,
+            plain.nodes
+        );
+        assert!(!cached.table.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn ordering_saves_nodes() {
+        let board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((4, 4), Piece::Queen(Queen::white())),
+            ((1, 6), Piece::Knight(Knight::white())),
+            ((0, 0), Piece::King(King::black())),
+            ((7, 4), Piece::Rook(Rook::black())),
+            ((2, 2), Piece::Bishop(Bishop::black())),
+        ]);
+        let mut unordered = Searcher {
+            ordering: false,
+            ..Default::default()
+        };
+        let mut ordered = Searcher::default();
+        let unordered_move = unordered.best_move(&board, &Color::White, 3);
+        let ordered_move = ordered.best_move(&board, &Color::White, 3);
+        assert_eq!(unordered_move, ordered_move);
+        assert!(
+            ordered.nodes < unordered.nodes,
+            "{} {}",
+            ordered.nodes,
+            unordered.nodes
+        );
+    }
+
+    #[test]
+    fn old_entries_are_dropped() {
+        let mut table = TranspositionTable::default();
+        table.insert(1, 3, 10, NodeType::Exact);
+        table.age();
+        table.insert(2, 1, 20, NodeType::LowerBound);
+        // a shallower search of this move replaces a deeper one from an older move
+        table.insert(1, 1, 15, NodeType::UpperBound);
+        assert_eq!(table.get(1), Some((1, 15, NodeType::UpperBound)));
+        table.insert(1, 0, 5, NodeType::Exact);
+        assert_eq!(table.get(1), Some((1, 15, NodeType::UpperBound)));
+
+        table.age();
+        table.age();
+        table.age();
+        assert!(table.is_empty());
+    }
+
+    /// Advances `step` every time it is read.
+    struct StepClock {
+        now: std::cell::Cell<Duration>,
+        step: Duration,
+    }
+
+    impl Clock for StepClock {
+        fn now(&self) -> Duration {
+            let now = self.now.get();
+            self.now.set(now + self.step);
+            now
+        }
+    }
+
+    fn timed_search(board: &Board, max: Duration) -> (Option<Move>, Searcher) {
+        let clock = StepClock {
+            now: Default::default(),
+            step: Duration::from_micros(1),
+        };
+        let mut searcher = Searcher::with_table();
+        let mv = searcher.best_move_timed(board, &Color::White, &clock, max);
+        let elapsed = clock.now.get();
+        // one extra read is allowed after the budget ran out
+        assert!(elapsed <= max + clock.step * 2, "{:?}", elapsed);
+        (mv, searcher)
+    }
+
+    #[test]
+    fn timed_search_deepens_with_more_time() {
+        let board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((4, 4), Piece::Queen(Queen::white())),
+            ((0, 0), Piece::King(King::black())),
+            ((7, 4), Piece::Rook(Rook::black())),
+        ]);
+        let legal = board.legal_moves(&Color::White);
+
+        let (short_move, short) = timed_search(&board, Duration::from_micros(5));
+        let ((fx, fy), (tx, ty)) = short_move.unwrap();
+        assert!(legal.contains(&(fx, fy, tx, ty)));
+
+        let (long_move, long) = timed_search(&board, Duration::from_millis(5));
+        assert!(long.depth > short.depth, "{} {}", long.depth, short.depth);
+        assert_eq!(long_move, Some(((4, 4), (7, 4))));
+    }
+}
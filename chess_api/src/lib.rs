@@ -1,5 +1,9 @@
+use std::{fmt, mem, sync::Arc};
+
 use serde::{Deserialize, Serialize};
 
+pub mod ai;
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Board {
     pub pieces: Vec<Option<Piece>>,
@@ -8,6 +12,91 @@ pub struct Board {
     pub take_squares: Vec<(usize, usize)>,
     pub move_squares: Vec<(usize, usize)>,
     pub turn: Color,
+    /// Hashes of the positions before each move since the last capture or pawn move.
+    #[serde(default)]
+    pub history: Vec<u64>,
+    /// Moves of both colors since the last capture or pawn move.
+    #[serde(default)]
+    pub halfmove_clock: usize,
+    /// Which sides may still castle, see `Board::WHITE_KINGSIDE` and the other bits.
+    #[serde(default = "all_castling_rights")]
+    pub castling_rights: u8,
+    /// Moves made since the start, with the piece a pawn was promoted to.
+    #[serde(default)]
+    pub moves: Vec<(Move, Option<Piece>)>,
+    /// What `Board::undo` goes back to.
+    #[serde(skip)]
+    pub previous: Previous,
+}
+
+/// The board before the last `apply_move`, for `Board::undo`. It is shared between clones, so
+/// copying a board to search from it stays cheap, and like the serialization, the comparisons
+/// leave it out: two boards are equal when their positions are.
+#[derive(Debug, Default, Clone)]
+pub struct Previous(Option<Arc<Board>>);
+
+impl PartialEq for Previous {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Previous {}
+
+fn all_castling_rights() -> u8 {
+    Board::ALL_CASTLING_RIGHTS
+}
+
+/// A move from the first square to the second one.
+pub type Move = ((usize, usize), (usize, usize));
+
+/// A first move, from and to, with the number of sequences starting with it, see `Board::perft_divide`.
+pub type PerftCount = ((usize, usize), (usize, usize), u64);
+
+/// Why `Board::apply_move` refused a move.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    NoPiece,
+    NotYourTurn,
+    IllegalMove,
+    InvalidPromotion,
+}
+
+/// Why a move can't be made, see `Board::why_illegal`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalReason {
+    NoPiece,
+    /// The piece belongs to the side that isn't moving.
+    WrongTurn,
+    CapturesOwnPiece,
+    /// The piece could get there if the squares in between were empty.
+    BlockedPath,
+    /// The piece doesn't move that way.
+    Unreachable,
+    LeavesKingInCheck,
+}
+
+/// How the game stands for the side to move, see `Board::game_state`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum GameState {
+    Ongoing,
+    /// The side to move is mated, the color is the winner.
+    Checkmate(Color),
+    Stalemate,
+    /// The position appeared three times, either player may claim the draw.
+    ThreefoldRepetition,
+    /// Fivefold repetition or seventy-five-move rule, the game is over without a claim.
+    ForcedDraw,
+}
+
+/// What a legal move does, see `Board::annotated_moves`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MoveFlags {
+    pub check: bool,
+    pub capture: bool,
+    pub castle: bool,
+    pub promotion: bool,
+    pub checkmate: bool,
 }
 
 pub fn starting_pieces() -> Vec<Option<Piece>> {
@@ -51,12 +140,70 @@ pub fn starting_pieces() -> Vec<Option<Piece>> {
 }
 
 impl Board {
+    pub const WHITE_KINGSIDE: u8 = 0b0001;
+    pub const WHITE_QUEENSIDE: u8 = 0b0010;
+    pub const BLACK_KINGSIDE: u8 = 0b0100;
+    pub const BLACK_QUEENSIDE: u8 = 0b1000;
+    pub const ALL_CASTLING_RIGHTS: u8 = 0b1111;
+
+    /// The castling rights lost when a piece leaves or is taken in the square.
+    /// The kings start on x = 3, so the kingside rooks start on x = 0.
+    fn castling_rights_of(square: (usize, usize)) -> u8 {
+        match square {
+            (3, 7) => Self::WHITE_KINGSIDE | Self::WHITE_QUEENSIDE,
+            (0, 7) => Self::WHITE_KINGSIDE,
+            (7, 7) => Self::WHITE_QUEENSIDE,
+            (3, 0) => Self::BLACK_KINGSIDE | Self::BLACK_QUEENSIDE,
+            (0, 0) => Self::BLACK_KINGSIDE,
+            (7, 0) => Self::BLACK_QUEENSIDE,
+            _ => 0,
+        }
+    }
+
+    /// The rook's move and the right used when a king castles from `from` to `to`.
+    fn castling_rook(from: (usize, usize), to: (usize, usize)) -> Option<(Move, u8)> {
+        match (from, to) {
+            ((3, 7), (1, 7)) => Some((((0, 7), (2, 7)), Self::WHITE_KINGSIDE)),
+            ((3, 7), (5, 7)) => Some((((7, 7), (4, 7)), Self::WHITE_QUEENSIDE)),
+            ((3, 0), (1, 0)) => Some((((0, 0), (2, 0)), Self::BLACK_KINGSIDE)),
+            ((3, 0), (5, 0)) => Some((((7, 0), (4, 0)), Self::BLACK_QUEENSIDE)),
+            _ => None,
+        }
+    }
+
+    /// The king of the given color can castle moving from `from` to `to`: the right is kept,
+    /// nothing is between the king and the rook, and the king isn't in check, doesn't cross an
+    /// attacked square and doesn't land on one.
+    pub fn can_castle(&self, color: &Color, from: &(usize, usize), to: &(usize, usize)) -> bool {
+        let Some(((rook_from, _), right)) = Self::castling_rook(*from, *to) else {
+            return false;
+        };
+        if self.castling_rights & right == 0 {
+            return false;
+        }
+        match (self.get(from), self.get(&rook_from)) {
+            (Some(Piece::King(king)), Some(Piece::Rook(rook)))
+                if &king.color == color && &rook.color == color => {}
+            _ => return false,
+        }
+        let y = from.1;
+        let between = from.0.min(rook_from.0) + 1..from.0.max(rook_from.0);
+        if between.into_iter().any(|x| self.get(&(x, y)).is_some()) {
+            return false;
+        }
+        let crossed = from.0.min(to.0)..=from.0.max(to.0);
+        !crossed
+            .into_iter()
+            .any(|x| self.is_square_attacked((x, y), &color.other()))
+    }
+
     pub fn get(&self, pos: &(usize, usize)) -> &Option<Piece> {
         &self.pieces[point_to_index(*pos)]
     }
 
     pub fn start(&mut self) {
         self.pieces = starting_pieces();
+        self.moves.clear();
     }
 
     fn get_king_pos(&self, color: &Color) -> Option<(usize, usize)> {
@@ -66,54 +213,427 @@ impl Board {
         })
     }
 
+    /// The given color is in check and can't make any legal move, whatever piece gives the check.
     pub fn is_check_mate(&self, color: &Color) -> bool {
-        if self.is_check(color) {
-            let king_pos = self.get_king_pos(color).unwrap();
-            let piece = self.get(&king_pos).as_ref().unwrap();
-            let mut possible_moves = Vec::with_capacity(8);
-
-            for pos in (0..64).map(index_to_point) {
-                if piece.can_move(self, &king_pos, &pos) || piece.can_take(self, &king_pos, &pos) {
-                    possible_moves.push(pos);
+        self.is_check(color) && !self.has_legal_move(color)
+    }
+
+    /// Position of a pawn that reached the last rank and must be promoted.
+    pub fn promotion_square(&self) -> Option<(usize, usize)> {
+        self.pieces.iter().enumerate().find_map(|(i, p)| {
+            let (x, y) = index_to_point(i);
+            match p {
+                Some(Piece::Pawn(pawn)) if pawn.color == Color::White && y == 0 => Some((x, y)),
+                Some(Piece::Pawn(pawn)) if pawn.color == Color::Black && y == 7 => Some((x, y)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Replaces the pawn in the promotion square with the given piece.
+    /// Returns false if there is no pawn to promote or the piece is not of the pawn's color.
+    pub fn promote(&mut self, piece: Piece) -> bool {
+        let Some(pos) = self.promotion_square() else {
+            return false;
+        };
+        let index = point_to_index(pos);
+        match (&self.pieces[index], &piece) {
+            (_, Piece::Pawn(_) | Piece::King(_)) => false,
+            (Some(pawn), piece) if pawn.color() == piece.color() => {
+                self.pieces[index] = Some(piece.clone());
+                if let Some((_, promoted)) = self.moves.last_mut() {
+                    *promoted = Some(piece.clone());
                 }
+                true
             }
+            _ => false,
+        }
+    }
 
-            for possible_move in possible_moves {
-                let mut cloned = self.clone();
-                cloned.turn = color.clone();
-                cloned.on_click(king_pos);
-                cloned.on_click(possible_move);
-                if !cloned.inner_is_check(color) {
-                    return false;
+    /// Times the current position has been reached, counting this one.
+    pub fn repetitions(&self) -> usize {
+        let hash = self.position_hash();
+        1 + self.history.iter().filter(|h| **h == hash).count()
+    }
+
+    /// The current position, castling rights and en passant square included, appeared three times.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 3
+    }
+
+    /// Threefold repetition or fifty-move rule, a player may claim the draw.
+    pub fn can_claim_draw(&self) -> bool {
+        self.is_threefold_repetition() || self.halfmove_clock >= 100
+    }
+
+    /// Fivefold repetition or seventy-five-move rule, the game is drawn without a claim.
+    pub fn is_forced_draw(&self) -> bool {
+        self.repetitions() >= 5 || self.halfmove_clock >= 150
+    }
+
+    /// The given color is not in check, but can't make any legal move.
+    pub fn is_stalemate(&self, color: &Color) -> bool {
+        !self.is_check(color) && !self.has_legal_move(color)
+    }
+
+    pub fn game_state(&self) -> GameState {
+        if self.has_legal_move(&self.turn) {
+            if self.is_forced_draw() {
+                GameState::ForcedDraw
+            } else if self.is_threefold_repetition() {
+                GameState::ThreefoldRepetition
+            } else {
+                GameState::Ongoing
+            }
+        } else if self.is_check(&self.turn) {
+            GameState::Checkmate(self.turn.other())
+        } else {
+            GameState::Stalemate
+        }
+    }
+
+    fn has_legal_move(&self, color: &Color) -> bool {
+        !self.legal_moves(color).is_empty()
+    }
+
+    /// Every move of the given color that doesn't leave its king in check, as (from-x, from-y, to-x, to-y).
+    /// Works on any position, the selection state of the board is ignored.
+    pub fn legal_moves(&self, color: &Color) -> Vec<(usize, usize, usize, usize)> {
+        let mut selecting = self.clone();
+        selecting.turn = color.clone();
+        selecting.piece_selected = None;
+        selecting.move_squares.clear();
+        selecting.take_squares.clear();
+        let mut moves = Vec::new();
+        for (from, _) in self.pieces.iter().enumerate().flat_map(|(x, p)| match p {
+            Some(piece) if piece.color() == color => Some((index_to_point(x), piece)),
+            _ => None,
+        }) {
+            let mut selected = selecting.clone();
+            selected.on_click(from);
+            for to in selected
+                .move_squares
+                .iter()
+                .chain(selected.take_squares.iter())
+            {
+                if selected.clone().on_click(*to) {
+                    moves.push((from.0, from.1, to.0, to.1));
                 }
             }
-            true
+        }
+        moves
+    }
+
+    /// The legal moves of the given color, flagged with what they do for the UI.
+    pub fn annotated_moves(&self, color: &Color) -> Vec<(Move, MoveFlags)> {
+        self.legal_moves(color)
+            .into_iter()
+            .filter_map(|(fx, fy, tx, ty)| {
+                let (from, to) = ((fx, fy), (tx, ty));
+                let after = self.after_move((from, to))?;
+                let piece = self.get(&from).as_ref()?;
+                let is_pawn = matches!(piece, Piece::Pawn(_));
+                let check = after.is_check(&color.other());
+                let flags = MoveFlags {
+                    check,
+                    capture: self.get(&to).is_some()
+                        || (is_pawn && fx != tx && self.en_passant_square == Some(to)),
+                    castle: matches!(piece, Piece::King(_)) && fx.abs_diff(tx) == 2,
+                    promotion: is_pawn && (ty == 0 || ty == 7),
+                    checkmate: check && after.legal_moves(&color.other()).is_empty(),
+                };
+                Some(((from, to), flags))
+            })
+            .collect()
+    }
+
+    /// The move of the side to move in standard algebraic notation, like "Nbd7", "exd6" or "e8=Q#".
+    /// None if the move isn't legal.
+    pub fn san(&self, (from, to): Move, promotion: Option<&Piece>) -> Option<String> {
+        let (_, flags) = self
+            .annotated_moves(&self.turn)
+            .into_iter()
+            .find(|(mv, _)| *mv == (from, to))?;
+        let piece = self.get(&from).as_ref()?;
+        let mut san = String::new();
+        if flags.castle {
+            // files are mirrored, the kingside rook is on x = 0
+            san.push_str(if to.0 < from.0 { "O-O" } else { "O-O-O" });
         } else {
-            false
+            let from_name = square_to_string(from);
+            if let Piece::Pawn(_) = piece {
+                if flags.capture {
+                    san.push_str(&from_name[..1]);
+                }
+            } else {
+                san.push(piece.to_fen_char().to_ascii_uppercase());
+                let others: Vec<_> = self
+                    .legal_moves(&self.turn)
+                    .into_iter()
+                    .map(|(fx, fy, tx, ty)| ((fx, fy), (tx, ty)))
+                    .filter(|&(other, other_to)| {
+                        other != from
+                            && other_to == to
+                            && self.get(&other).as_ref().map(Piece::to_fen_char)
+                                == Some(piece.to_fen_char())
+                    })
+                    .map(|(other, _)| other)
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|other| other.0 != from.0) {
+                        san.push_str(&from_name[..1]);
+                    } else if others.iter().all(|other| other.1 != from.1) {
+                        san.push_str(&from_name[1..]);
+                    } else {
+                        san.push_str(&from_name);
+                    }
+                }
+            }
+            if flags.capture {
+                san.push('x');
+            }
+            san.push_str(&square_to_string(to));
+            if flags.promotion {
+                let promoted = promotion.map_or('Q', Piece::to_fen_char);
+                san.push('=');
+                san.push(promoted.to_ascii_uppercase());
+            }
         }
+        let mut after = self.clone();
+        after.apply_move(from, to, promotion.cloned()).ok()?;
+        if after.is_check_mate(&after.turn) {
+            san.push('#');
+        } else if after.is_check(&after.turn) {
+            san.push('+');
+        }
+        Some(san)
+    }
+
+    /// The moves made since the start in standard algebraic notation, numbered like the movetext
+    /// of a PGN. None if they can't be replayed from the starting position.
+    pub fn movetext(&self) -> Option<String> {
+        let mut board = Board::default();
+        let mut text = Vec::new();
+        for (i, ((from, to), promotion)) in self.moves.iter().enumerate() {
+            let san = board.san((*from, *to), promotion.as_ref())?;
+            if i % 2 == 0 {
+                text.push(format!("{}. {san}", i / 2 + 1));
+            } else {
+                text.push(san);
+            }
+            board.apply_move(*from, *to, promotion.clone()).ok()?;
+        }
+        Some(text.join(" "))
+    }
+
+    /// Makes a move of the side to move without going through the selection, promoting to
+    /// `promotion` or to a queen if none is given. The selection and its squares are kept as they
+    /// were, and the board is left untouched on errors.
+    pub fn apply_move(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        promotion: Option<Piece>,
+    ) -> Result<(), MoveError> {
+        let color = match self.get(&from) {
+            Some(piece) if piece.color() == &self.turn => piece.color().clone(),
+            Some(_) => return Err(MoveError::NotYourTurn),
+            None => return Err(MoveError::NoPiece),
+        };
+        let mut board = self.clone();
+        board.piece_selected = None;
+        board.move_squares.clear();
+        board.take_squares.clear();
+        board.on_click(from);
+        if !board.on_click(to) {
+            return Err(MoveError::IllegalMove);
+        }
+        if board.promotion_square().is_some() {
+            let piece = promotion.unwrap_or(Piece::Queen(Queen::default()).with_color(color));
+            if !board.promote(piece) {
+                return Err(MoveError::InvalidPromotion);
+            }
+        }
+        board.piece_selected = self.piece_selected;
+        board.move_squares.clone_from(&self.move_squares);
+        board.take_squares.clone_from(&self.take_squares);
+        let previous = mem::replace(self, board);
+        self.previous = Previous(Some(Arc::new(previous)));
+        Ok(())
+    }
+
+    /// Takes back the last move made with `apply_move`, and anything done since, return false
+    /// if there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.previous.0.take() else {
+            return false;
+        };
+        *self = Arc::unwrap_or_clone(previous);
+        true
+    }
+
+    /// Number of move sequences of the given length from this position, promotions count once per piece.
+    pub fn perft(&self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        self.successors()
+            .iter()
+            .map(|(_, board)| board.perft(depth - 1))
+            .sum()
+    }
+
+    /// `perft` split by the first move, to find where a move generator goes wrong.
+    pub fn perft_divide(&self, depth: usize) -> Vec<PerftCount> {
+        let mut divided: Vec<PerftCount> = Vec::new();
+        for ((from, to), board) in self.successors() {
+            let nodes = board.perft(depth.saturating_sub(1));
+            match divided.last_mut() {
+                // the other promotions of the same move
+                Some(last) if last.0 == from && last.1 == to => last.2 += nodes,
+                _ => divided.push((from, to, nodes)),
+            }
+        }
+        divided
+    }
+
+    /// The boards after every legal move of the side to move, once per piece for promotions.
+    fn successors(&self) -> Vec<(Move, Board)> {
+        let mut successors = Vec::new();
+        for (fx, fy, tx, ty) in self.legal_moves(&self.turn) {
+            let mv = ((fx, fy), (tx, ty));
+            let mut board = self.clone();
+            board.piece_selected = None;
+            board.on_click(mv.0);
+            if !board.on_click(mv.1) {
+                continue;
+            }
+            if board.promotion_square().is_none() {
+                successors.push((mv, board));
+                continue;
+            }
+            let color = &self.turn;
+            for piece in ['q', 'r', 'b', 'n'] {
+                let piece = match color {
+                    Color::White => piece.to_ascii_uppercase(),
+                    Color::Black => piece,
+                };
+                let mut promoted = board.clone();
+                promoted.promote(Piece::from_fen_char(piece).unwrap());
+                successors.push((mv, promoted));
+            }
+        }
+        successors
     }
 
     pub fn is_check(&self, color: &Color) -> bool {
-        self.clone().inner_is_check(color)
+        self.inner_is_check(color)
+    }
+
+    fn inner_is_check(&self, color: &Color) -> bool {
+        match self.get_king_pos(color) {
+            Some(king_pos) => self.is_square_attacked(king_pos, &color.other()),
+            None => false,
+        }
+    }
+
+    pub fn is_square_attacked(&self, square: (usize, usize), by: &Color) -> bool {
+        !self.attackers_of(square, by).is_empty()
+    }
+
+    /// Squares of the pieces of the given color attacking the square, whatever is in it or whose turn it is.
+    pub fn attackers_of(&self, square: (usize, usize), color: &Color) -> Vec<(usize, usize)> {
+        self.pieces
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| match p {
+                Some(piece) if piece.color() == color => Some((index_to_point(i), piece)),
+                _ => None,
+            })
+            .filter(|(from, piece)| *from != square && piece.attacks(self, from, &square))
+            .map(|(from, _)| from)
+            .collect()
     }
 
-    /// This set the current turn to the oposite color to allow the can_take method.
-    fn inner_is_check(&mut self, color: &Color) -> bool {
-        self.turn = color.other();
-        let king_pos = match self.get_king_pos(color) {
-            Some(pos) => pos,
-            None => return false,
+    /// Why the side to move can't play `from` to `to`, or None if it can. The board isn't changed.
+    pub fn why_illegal(&self, from: (usize, usize), to: (usize, usize)) -> Option<IllegalReason> {
+        let Some(piece) = self.get(&from) else {
+            return Some(IllegalReason::NoPiece);
         };
+        if piece.color() != &self.turn {
+            return Some(IllegalReason::WrongTurn);
+        }
+        if matches!(self.get(&to), Some(target) if target.color() == piece.color()) {
+            return Some(IllegalReason::CapturesOwnPiece);
+        }
+        if self
+            .legal_moves(&self.turn)
+            .contains(&(from.0, from.1, to.0, to.1))
+        {
+            return None;
+        }
+        let reaches =
+            |board: &Board| piece.can_move(board, &from, &to) || piece.can_take(board, &from, &to);
+        if reaches(self) {
+            return Some(IllegalReason::LeavesKingInCheck);
+        }
+        let mut open = self.clone();
+        for square in squares_between(from, to) {
+            open.pieces[point_to_index(square)] = None;
+        }
+        match reaches(&open) {
+            true => Some(IllegalReason::BlockedPath),
+            false => Some(IllegalReason::Unreachable),
+        }
+    }
 
-        for (from, piece) in self.pieces.iter().enumerate().flat_map(|(x, p)| match p {
-            Some(piece) if piece.color() != color => Some((index_to_point(x), piece)),
-            _ => None,
-        }) {
-            if piece.can_take(self, &from, &king_pos) {
-                return true;
+    /// Squares of the pieces of the given color that stand alone between their king and an enemy
+    /// bishop, rook or queen, so they can only move along that line.
+    pub fn pinned_pieces(&self, color: &Color) -> Vec<(usize, usize)> {
+        let Some(king) = self.get_king_pos(color) else {
+            return Vec::new();
+        };
+        let mut pinned = Vec::new();
+        for (dx, dy) in [
+            (0, 1),
+            (0, -1),
+            (1, 0),
+            (-1, 0),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ] {
+            let diagonal = dx != 0 && dy != 0;
+            let mut blocker = None;
+            let mut square = king;
+            while let (Some(x), Some(y)) = (
+                square.0.checked_add_signed(dx).filter(|x| *x < 8),
+                square.1.checked_add_signed(dy).filter(|y| *y < 8),
+            ) {
+                square = (x, y);
+                match self.get(&square) {
+                    None => continue,
+                    Some(piece) if piece.color() == color => match blocker {
+                        None => blocker = Some(square),
+                        Some(_) => break,
+                    },
+                    Some(piece) => {
+                        let slides = match piece {
+                            Piece::Queen(_) => true,
+                            Piece::Bishop(_) => diagonal,
+                            Piece::Rook(_) => !diagonal,
+                            _ => false,
+                        };
+                        if slides {
+                            pinned.extend(blocker);
+                        }
+                        break;
+                    }
+                }
             }
         }
-        false
+        pinned
     }
 
     pub fn on_click(&mut self, from: (usize, usize)) -> bool {
@@ -130,8 +650,30 @@ impl Board {
     /// Returns true if a piece moved
     fn inner_on_click(&mut self, from: (usize, usize)) -> bool {
         let piece = &self.pieces[point_to_index(from)];
-        if self.move_squares.contains(&from) || self.take_squares.contains(&from) {
+        // a selection left over from the other side can't move
+        let selected_to_move = matches!(
+            self.piece_selected.map(|selected| self.get(&selected)),
+            Some(Some(selected)) if selected.color() == &self.turn
+        );
+        if selected_to_move
+            && (self.move_squares.contains(&from) || self.take_squares.contains(&from))
+        {
             let piece_selected = self.piece_selected.unwrap();
+            let is_pawn = matches!(
+                self.pieces[point_to_index(piece_selected)],
+                Some(Piece::Pawn(_))
+            );
+            if is_pawn || self.pieces[point_to_index(from)].is_some() {
+                // the previous positions can't be repeated anymore
+                self.history.clear();
+                self.halfmove_clock = 0;
+            } else {
+                self.history.push(self.position_hash());
+                self.halfmove_clock += 1;
+            }
+            self.castling_rights &=
+                !(Self::castling_rights_of(piece_selected) | Self::castling_rights_of(from));
+            self.moves.push(((piece_selected, from), None));
             // handle en passant take
             if let Some(en_passant) = self.en_passant_square {
                 if let Some(Piece::Pawn(p)) = &self.pieces[point_to_index(piece_selected)] {
@@ -143,6 +685,16 @@ impl Board {
                     }
                 }
             }
+            // handle castling, the rook jumps over the king
+            if let Some(Piece::King(_)) = &self.pieces[point_to_index(piece_selected)] {
+                if let Some(((rook_from, rook_to), _)) = Self::castling_rook(piece_selected, from) {
+                    self.pieces
+                        .swap(point_to_index(rook_from), point_to_index(rook_to));
+                    if let Some(Piece::Rook(rook)) = &mut self.pieces[point_to_index(rook_to)] {
+                        rook.has_moved = true;
+                    }
+                }
+            }
             self.pieces[point_to_index(piece_selected)]
                 .as_mut()
                 .unwrap()
@@ -187,11 +739,66 @@ impl Default for Board {
             take_squares: Vec::new(),
             move_squares: Vec::new(),
             en_passant_square: None,
+            history: Vec::new(),
+            halfmove_clock: 0,
+            castling_rights: Self::ALL_CASTLING_RIGHTS,
+            moves: Vec::new(),
+            previous: Previous::default(),
             turn: Color::White,
         }
     }
 }
 
+/// ASCII diagram from rank 8 down to rank 1, white pieces in uppercase and empty squares as '.'.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..8 {
+            write!(f, "{}", 8 - y)?;
+            // files are mirrored, file a is on x = 7
+            for x in (0..8).rev() {
+                let c = self.get(&(x, y)).as_ref().map_or('.', Piece::to_fen_char);
+                write!(f, " {c}")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "  a b c d e f g h")?;
+        write!(f, "{} to move", self.turn.name())
+    }
+}
+
+/// The squares strictly between two squares on the same line, column or diagonal.
+fn squares_between(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (dx, dy) = (
+        to.0 as isize - from.0 as isize,
+        to.1 as isize - from.1 as isize,
+    );
+    if !(dx == 0 || dy == 0 || dx.abs() == dy.abs()) {
+        return Vec::new();
+    }
+    let steps = dx.abs().max(dy.abs());
+    (1..steps)
+        .map(|i| {
+            (
+                from.0.wrapping_add_signed(dx.signum() * i),
+                from.1.wrapping_add_signed(dy.signum() * i),
+            )
+        })
+        .collect()
+}
+
+/// The square of a name like "e4". Files are mirrored, file a is x = 7, and rank 8 is y = 0.
+pub fn parse_square(name: &str) -> Option<(usize, usize)> {
+    let &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] = name.as_bytes() else {
+        return None;
+    };
+    Some((7 - (file - b'a') as usize, 8 - (rank - b'0') as usize))
+}
+
+/// The name of a square like "e4", the inverse of `parse_square`.
+pub fn square_to_string((x, y): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + 7 - x as u8) as char, 8 - y)
+}
+
 pub fn index_to_point(index: usize) -> (usize, usize) {
     (index % 8, index / 8)
 }
@@ -199,7 +806,7 @@ pub fn point_to_index((x, y): (usize, usize)) -> usize {
     x + y * 8
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum Piece {
     Pawn(Pawn),
     Knight(Knight),
@@ -209,7 +816,7 @@ pub enum Piece {
     King(King),
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub enum Color {
     Black,
     #[default]
@@ -234,6 +841,60 @@ impl Color {
 }
 
 impl Piece {
+    /// Parses a FEN piece letter, uppercase for white and lowercase for black.
+    pub fn from_fen_char(c: char) -> Option<Piece> {
+        let color = match c.is_ascii_uppercase() {
+            true => Color::White,
+            false => Color::Black,
+        };
+        let white = color == Color::White;
+        let piece = match c.to_ascii_lowercase() {
+            'p' if white => Piece::Pawn(Pawn::white()),
+            'p' => Piece::Pawn(Pawn::black()),
+            'n' if white => Piece::Knight(Knight::white()),
+            'n' => Piece::Knight(Knight::black()),
+            'b' if white => Piece::Bishop(Bishop::white()),
+            'b' => Piece::Bishop(Bishop::black()),
+            'r' if white => Piece::Rook(Rook::white()),
+            'r' => Piece::Rook(Rook::black()),
+            'q' if white => Piece::Queen(Queen::white()),
+            'q' => Piece::Queen(Queen::black()),
+            'k' if white => Piece::King(King::white()),
+            'k' => Piece::King(King::black()),
+            _ => return None,
+        };
+        Some(piece)
+    }
+
+    pub fn to_fen_char(&self) -> char {
+        let c = match self {
+            Piece::Pawn(_) => 'p',
+            Piece::Knight(_) => 'n',
+            Piece::Bishop(_) => 'b',
+            Piece::Rook(_) => 'r',
+            Piece::Queen(_) => 'q',
+            Piece::King(_) => 'k',
+        };
+        match self.color() {
+            Color::White => c.to_ascii_uppercase(),
+            Color::Black => c,
+        }
+    }
+
+    /// A copy of the piece with the given color.
+    pub fn with_color(&self, color: Color) -> Piece {
+        let mut piece = self.clone();
+        match &mut piece {
+            Piece::Pawn(p) => p.color = color,
+            Piece::Knight(n) => n.color = color,
+            Piece::Bishop(b) => b.color = color,
+            Piece::Rook(r) => r.color = color,
+            Piece::Queen(q) => q.color = color,
+            Piece::King(k) => k.color = color,
+        }
+        piece
+    }
+
     pub fn class_name(&self) -> String {
         match self {
             Piece::Pawn(p) => format!("{}_pawn", p.color.name()),
@@ -256,6 +917,18 @@ impl Piece {
         }
     }
 
+    /// The piece in `from` could take a piece in `to`, ignoring what is in `to` and the turn.
+    pub fn attacks(&self, board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
+        match self {
+            Piece::Pawn(p) => p.can_take(board, from, to),
+            Piece::Knight(n) => n.can_take(board, from, to),
+            Piece::Bishop(b) => b.can_take(board, from, to),
+            Piece::Rook(r) => r.can_take(board, from, to),
+            Piece::Queen(q) => q.can_take(board, from, to),
+            Piece::King(k) => k.can_take(board, from, to),
+        }
+    }
+
     pub fn can_take(&self, board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
         if let (Some(piece1), Some(piece2)) = (board.get(from), board.get(to)) {
             if piece1.color() == piece2.color() {
@@ -279,14 +952,7 @@ impl Piece {
             }
             return false;
         }
-        match self {
-            Piece::Pawn(p) => p.can_take(board, from, to),
-            Piece::Knight(n) => n.can_take(board, from, to),
-            Piece::Bishop(b) => b.can_take(board, from, to),
-            Piece::Rook(r) => r.can_take(board, from, to),
-            Piece::Queen(q) => q.can_take(board, from, to),
-            Piece::King(k) => k.can_take(board, from, to),
-        }
+        self.attacks(board, from, to)
     }
 
     pub fn can_move(&self, board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
@@ -357,21 +1023,26 @@ impl<T: Same> Moves for T {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Pawn {
     color: Color,
 }
 
 impl Moves for Pawn {
-    fn can_move(&self, _board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
+    fn can_move(&self, board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
+        // the double step can't jump over a piece
         match self.color {
             Color::White => {
                 (to.1 + 1 == from.1 && to.0 == from.0)
-                    || (from.1 == 6 && (to.1 + 2 == from.1 && to.0 == from.0))
+                    || (from.1 == 6
+                        && (to.1 + 2 == from.1 && to.0 == from.0)
+                        && board.get(&(from.0, 5)).is_none())
             }
             Color::Black => {
                 (to.1 == from.1 + 1 && to.0 == from.0)
-                    || (from.1 == 1 && (to.1 == from.1 + 2 && to.0 == from.0))
+                    || (from.1 == 1
+                        && (to.1 == from.1 + 2 && to.0 == from.0)
+                        && board.get(&(from.0, 2)).is_none())
             }
         }
     }
@@ -384,7 +1055,7 @@ impl Moves for Pawn {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Knight {
     color: Color,
 }
@@ -397,7 +1068,7 @@ impl Same for Knight {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Bishop {
     color: Color,
 }
@@ -428,7 +1099,7 @@ impl Same for Bishop {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Rook {
     color: Color,
     has_moved: bool,
@@ -472,7 +1143,7 @@ impl Same for Rook {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Queen {
     color: Color,
 }
@@ -484,14 +1155,18 @@ impl Same for Queen {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub struct King {
     color: Color,
     has_moved: bool,
 }
 
-impl Same for King {
-    fn can(&self, _board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
+impl Moves for King {
+    fn can_move(&self, board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
+        self.can_take(board, from, to) || board.can_castle(&self.color, from, to)
+    }
+
+    fn can_take(&self, _board: &Board, from: &(usize, usize), to: &(usize, usize)) -> bool {
         if from == to {
             return false;
         }
@@ -531,3 +1206,679 @@ macro_rules! colored {
         }
     };
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// An empty board with only the given pieces, white to move.
+    pub(crate) fn board_with(pieces: &[((usize, usize), Piece)]) -> Board {
+        let mut board = Board {
+            pieces: vec![None; 64],
+            ..Default::default()
+        };
+        for (pos, piece) in pieces {
+            board.pieces[point_to_index(*pos)] = Some(piece.clone());
+        }
+        board
+    }
+
+    #[test]
+    fn stalemate_in_the_corner() {
+        let mut board = board_with(&[
+            ((7, 0), Piece::King(King::black())),
+            ((5, 1), Piece::Queen(Queen::white())),
+            ((0, 7), Piece::King(King::white())),
+        ]);
+        board.turn = Color::Black;
+        assert!(board.is_stalemate(&Color::Black));
+        assert!(!board.is_check_mate(&Color::Black));
+        assert_eq!(board.game_state(), GameState::Stalemate);
+
+        // white to move isn't stalemated
+        board.turn = Color::White;
+        assert!(!board.is_stalemate(&Color::White));
+        assert_eq!(board.game_state(), GameState::Ongoing);
+
+        // with the queen giving check it's mate
+        board.pieces[point_to_index((5, 1))] = None;
+        board.pieces[point_to_index((6, 1))] = Some(Piece::Queen(Queen::white()));
+        board.pieces[point_to_index((5, 2))] = Some(Piece::King(King::white()));
+        board.pieces[point_to_index((0, 7))] = None;
+        board.turn = Color::Black;
+        assert_eq!(board.game_state(), GameState::Checkmate(Color::White));
+        assert_eq!(Board::default().game_state(), GameState::Ongoing);
+    }
+
+    #[test]
+    fn under_promotion_to_a_checking_knight() {
+        let mut board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((4, 1), Piece::Pawn(Pawn::white())),
+            ((2, 1), Piece::King(King::black())),
+        ]);
+
+        let mut queened = board.clone();
+        queened.apply_move((4, 1), (4, 0), None).unwrap();
+        assert_eq!(queened.get(&(4, 0)), &Some(Piece::Queen(Queen::white())));
+        assert!(!queened.is_check(&Color::Black));
+
+        board
+            .apply_move((4, 1), (4, 0), Some(Piece::Knight(Knight::white())))
+            .unwrap();
+        assert_eq!(board.get(&(4, 0)), &Some(Piece::Knight(Knight::white())));
+        assert_eq!(board.promotion_square(), None);
+        assert!(board.is_check(&Color::Black));
+        assert!(!board.is_check_mate(&Color::Black));
+
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+
+        // the black pieces take every escape and none can take the knight
+        let mut board = board_with(&[
+            ((0, 7), Piece::King(King::white())),
+            ((5, 1), Piece::Pawn(Pawn::white())),
+            ((7, 1), Piece::King(King::black())),
+            ((6, 0), Piece::Knight(Knight::black())),
+            ((7, 0), Piece::Bishop(Bishop::black())),
+            ((6, 1), Piece::Pawn(Pawn::black())),
+            ((7, 2), Piece::Pawn(Pawn::black())),
+        ]);
+        let mut queened = board.clone();
+        queened.apply_move((5, 1), (5, 0), None).unwrap();
+        assert!(!queened.is_check(&Color::Black));
+        board
+            .apply_move((5, 1), (5, 0), Some(Piece::Knight(Knight::white())))
+            .unwrap();
+        assert!(board.is_check_mate(&Color::Black));
+    }
+
+    #[test]
+    fn recolor_pieces() {
+        let knight = Piece::Knight(Knight::white()).with_color(Color::Black);
+        assert_eq!(knight, Piece::Knight(Knight::black()));
+        assert_eq!(knight.with_color(Color::White).to_fen_char(), 'N');
+    }
+
+    fn castling_board() -> Board {
+        board_with(&[
+            ((3, 7), Piece::King(King::white())),
+            ((0, 7), Piece::Rook(Rook::white())),
+            ((7, 7), Piece::Rook(Rook::white())),
+            ((6, 2), Piece::King(King::black())),
+        ])
+    }
+
+    #[test]
+    fn castling_moves_the_rook() {
+        let board = castling_board();
+        let moves = board.legal_moves(&Color::White);
+        assert!(moves.contains(&(3, 7, 1, 7)));
+        assert!(moves.contains(&(3, 7, 5, 7)));
+
+        let castled = board.after_move(((3, 7), (1, 7))).unwrap();
+        assert!(matches!(castled.get(&(1, 7)), Some(Piece::King(_))));
+        assert!(matches!(castled.get(&(2, 7)), Some(Piece::Rook(_))));
+        assert_eq!(castled.get(&(0, 7)), &None);
+        assert_eq!(castled.castling_rights & Board::WHITE_QUEENSIDE, 0);
+    }
+
+    #[test]
+    fn castling_through_clicks() {
+        let mut board = castling_board();
+        board.pieces[point_to_index((6, 2))] = None;
+        board.pieces[point_to_index((3, 0))] = Some(Piece::King(King::black()));
+        board.pieces[point_to_index((0, 0))] = Some(Piece::Rook(Rook::black()));
+        board.pieces[point_to_index((7, 0))] = Some(Piece::Rook(Rook::black()));
+        board.en_passant_square = Some((4, 5));
+        board.turn = Color::Black;
+
+        board.on_click((3, 0));
+        assert!(board.move_squares.contains(&(1, 0)));
+        assert!(board.move_squares.contains(&(5, 0)));
+        assert!(board.on_click((5, 0)));
+        assert!(matches!(board.get(&(5, 0)), Some(Piece::King(_))));
+        assert!(matches!(board.get(&(4, 0)), Some(Piece::Rook(_))));
+        assert_eq!(board.get(&(7, 0)), &None);
+        assert_eq!(board.turn, Color::White);
+        assert_eq!(board.en_passant_square, None);
+        assert_eq!(
+            board.castling_rights,
+            Board::WHITE_KINGSIDE | Board::WHITE_QUEENSIDE
+        );
+
+        board.on_click((3, 7));
+        assert!(board.move_squares.contains(&(1, 7)));
+        // the black rook that castled attacks the queenside now
+        assert!(!board.move_squares.contains(&(5, 7)));
+        assert!(board.on_click((1, 7)));
+        assert!(matches!(board.get(&(2, 7)), Some(Piece::Rook(_))));
+        assert_eq!(board.castling_rights, 0);
+        // the king that castled can't do it again
+        board.on_click((1, 0));
+        assert!(!board.move_squares.contains(&(3, 0)));
+    }
+
+    #[test]
+    fn no_castling_through_attacked_squares() {
+        let mut board = castling_board();
+        // attacks the square the king crosses going kingside
+        board.pieces[point_to_index((2, 0))] = Some(Piece::Rook(Rook::black()));
+        assert!(board.is_square_attacked((2, 7), &Color::Black));
+        assert!(!board.is_square_attacked((4, 7), &Color::Black));
+        let moves = board.legal_moves(&Color::White);
+        assert!(!moves.contains(&(3, 7, 1, 7)));
+        assert!(moves.contains(&(3, 7, 5, 7)));
+
+        // nor out of check
+        board.pieces[point_to_index((2, 0))] = None;
+        board.pieces[point_to_index((3, 0))] = Some(Piece::Rook(Rook::black()));
+        let moves = board.legal_moves(&Color::White);
+        assert!(!moves.contains(&(3, 7, 1, 7)));
+        assert!(!moves.contains(&(3, 7, 5, 7)));
+    }
+
+    #[test]
+    fn castling_rights_follow_kings_and_rooks() {
+        let board = board_with(&[
+            ((3, 7), Piece::King(King::white())),
+            ((0, 7), Piece::Rook(Rook::white())),
+            ((7, 7), Piece::Rook(Rook::white())),
+            ((3, 0), Piece::King(King::black())),
+            ((0, 0), Piece::Rook(Rook::black())),
+            ((5, 5), Piece::Bishop(Bishop::black())),
+        ]);
+
+        // the bishop takes the queenside rook on its home square
+        let board = board.after_move(((5, 5), (7, 7))).unwrap();
+        assert_eq!(
+            board.castling_rights,
+            Board::WHITE_KINGSIDE | Board::BLACK_KINGSIDE | Board::BLACK_QUEENSIDE
+        );
+
+        let board = board.after_move(((0, 0), (0, 3))).unwrap();
+        assert_eq!(
+            board.castling_rights,
+            Board::WHITE_KINGSIDE | Board::BLACK_QUEENSIDE
+        );
+
+        // moving back doesn't give the right back
+        let board = board.after_move(((3, 7), (2, 7))).unwrap();
+        let board = board.after_move(((2, 7), (3, 7))).unwrap();
+        assert_eq!(board.castling_rights, Board::BLACK_QUEENSIDE);
+    }
+
+    #[test]
+    fn legal_moves_ignore_the_selection() {
+        let mut board = Board::default();
+        let fresh = board.legal_moves(&Color::White);
+        assert_eq!(fresh.len(), 20);
+        assert_eq!(board.legal_moves(&Color::Black).len(), 20);
+
+        board.on_click((1, 7));
+        let selected = board.clone();
+        assert_eq!(board.legal_moves(&Color::White), fresh);
+        assert_eq!(board, selected);
+    }
+
+    #[test]
+    fn pinned_pieces_stay_on_the_pin() {
+        let board = board_with(&[
+            ((3, 7), Piece::King(King::white())),
+            ((3, 5), Piece::Rook(Rook::white())),
+            ((3, 0), Piece::Rook(Rook::black())),
+            ((7, 0), Piece::King(King::black())),
+        ]);
+        let rook_moves = sorted(
+            board
+                .legal_moves(&Color::White)
+                .into_iter()
+                .filter(|(fx, fy, _, _)| (*fx, *fy) == (3, 5))
+                .map(|(_, _, tx, ty)| (tx, ty))
+                .collect(),
+        );
+        assert_eq!(
+            rook_moves,
+            vec![(3, 0), (3, 1), (3, 2), (3, 3), (3, 4), (3, 6)]
+        );
+    }
+
+    #[test]
+    fn display_the_starting_position() {
+        let mut board = Board::default();
+        assert_eq!(
+            board.to_string(),
+            "8 r n b q k b n r\n\
+             7 p p p p p p p p\n\
+             6 . . . . . . . .\n\
+             5 . . . . . . . .\n\
+             4 . . . . . . . .\n\
+             3 . . . . . . . .\n\
+             2 P P P P P P P P\n\
+             1 R N B Q K B N R\n  \
+             a b c d e f g h\n\
+             white to move"
+        );
+
+        board.apply_move((3, 6), (3, 4), None).unwrap();
+        let lines = board.to_string();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines[4], "4 . . . . P . . .");
+        assert_eq!(lines[6], "2 P P P P . P P P");
+        assert_eq!(lines[9], "black to move");
+    }
+
+    #[test]
+    fn bishop_pins_a_knight() {
+        let mut board = board_with(&[
+            ((3, 7), Piece::King(King::white())),
+            ((2, 6), Piece::Knight(Knight::white())),
+            ((0, 4), Piece::Bishop(Bishop::black())),
+            ((7, 0), Piece::King(King::black())),
+        ]);
+        assert_eq!(board.pinned_pieces(&Color::White), vec![(2, 6)]);
+        assert!(board.pinned_pieces(&Color::Black).is_empty());
+        assert!(board
+            .legal_moves(&Color::White)
+            .iter()
+            .all(|(fx, fy, _, _)| (*fx, *fy) != (2, 6)));
+
+        // a second blocker breaks the pin
+        board.pieces[point_to_index((1, 5))] = Some(Piece::Pawn(Pawn::white()));
+        assert!(board.pinned_pieces(&Color::White).is_empty());
+
+        // rooks don't pin along diagonals
+        board.pieces[point_to_index((1, 5))] = None;
+        board.pieces[point_to_index((0, 4))] = Some(Piece::Rook(Rook::black()));
+        assert!(board.pinned_pieces(&Color::White).is_empty());
+    }
+
+    #[test]
+    fn reasons_for_illegal_moves() {
+        let mut board = Board::default();
+        assert_eq!(board.why_illegal((3, 6), (3, 4)), None);
+        assert_eq!(
+            board.why_illegal((3, 4), (3, 3)),
+            Some(IllegalReason::NoPiece)
+        );
+        assert_eq!(
+            board.why_illegal((3, 1), (3, 3)),
+            Some(IllegalReason::WrongTurn)
+        );
+        assert_eq!(
+            board.why_illegal((0, 7), (0, 6)),
+            Some(IllegalReason::CapturesOwnPiece)
+        );
+        assert_eq!(
+            board.why_illegal((0, 7), (0, 5)),
+            Some(IllegalReason::BlockedPath)
+        );
+        assert_eq!(
+            board.why_illegal((1, 7), (1, 5)),
+            Some(IllegalReason::Unreachable)
+        );
+
+        let start = board.clone();
+        let pinned = board_with(&[
+            ((3, 7), Piece::King(King::white())),
+            ((2, 6), Piece::Knight(Knight::white())),
+            ((0, 4), Piece::Bishop(Bishop::black())),
+            ((7, 0), Piece::King(King::black())),
+        ]);
+        assert_eq!(
+            pinned.why_illegal((2, 6), (0, 5)),
+            Some(IllegalReason::LeavesKingInCheck)
+        );
+        board.on_click((3, 6));
+        assert_eq!(board.why_illegal((3, 6), (3, 4)), None);
+        assert_eq!(board.pieces, start.pieces);
+    }
+
+    #[test]
+    fn square_names() {
+        assert_eq!(parse_square("e1"), Some((3, 7)));
+        assert_eq!(parse_square("d8"), Some((4, 0)));
+        assert_eq!(parse_square("a1"), Some((7, 7)));
+        assert_eq!(parse_square("h8"), Some((0, 0)));
+        assert!(matches!(
+            Board::default().get(&parse_square("e1").unwrap()),
+            Some(Piece::King(_))
+        ));
+        for name in ["", "e", "e9", "i1", "e0", "E4", "e44"] {
+            assert_eq!(parse_square(name), None, "{name}");
+        }
+        for index in 0..64 {
+            let square = index_to_point(index);
+            assert_eq!(parse_square(&square_to_string(square)), Some(square));
+        }
+    }
+
+    #[test]
+    fn moves_in_algebraic_notation() {
+        let board = castling_board();
+        assert_eq!(board.san(((3, 7), (1, 7)), None).unwrap(), "O-O");
+        assert_eq!(board.san(((3, 7), (5, 7)), None).unwrap(), "O-O-O");
+        assert_eq!(board.san(((3, 7), (3, 3)), None), None);
+
+        // both rooks reach d1 and a3 can be reached from a1 and a5
+        let board = board_with(&[
+            ((3, 3), Piece::King(King::white())),
+            ((3, 0), Piece::King(King::black())),
+            ((7, 7), Piece::Rook(Rook::white())),
+            ((0, 7), Piece::Rook(Rook::white())),
+            ((7, 3), Piece::Rook(Rook::white())),
+        ]);
+        assert_eq!(board.san(((7, 7), (4, 7)), None).unwrap(), "Rad1");
+        assert_eq!(board.san(((7, 7), (7, 5)), None).unwrap(), "R1a3");
+        assert_eq!(board.san(((7, 3), (7, 5)), None).unwrap(), "R5a3");
+        assert_eq!(board.san(((0, 7), (0, 0)), None).unwrap(), "Rh8+");
+
+        let board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((4, 1), Piece::Pawn(Pawn::white())),
+            ((2, 1), Piece::King(King::black())),
+        ]);
+        let knight = Piece::Knight(Knight::white());
+        assert_eq!(board.san(((4, 1), (4, 0)), None).unwrap(), "d8=Q");
+        assert_eq!(board.san(((4, 1), (4, 0)), Some(&knight)).unwrap(), "d8=N+");
+    }
+
+    #[test]
+    fn movetext_replays_the_game() {
+        let mut board = Board::default();
+        board.apply_move((3, 6), (3, 4), None).unwrap();
+        board.apply_move((4, 1), (4, 3), None).unwrap();
+        board.apply_move((3, 4), (4, 3), None).unwrap();
+        board.on_click((6, 0));
+        board.on_click((5, 2));
+        assert_eq!(board.movetext().unwrap(), "1. e4 d5 2. exd5 Nc6");
+
+        let before = board.clone();
+        board.apply_move((4, 3), (4, 2), None).unwrap();
+        assert_eq!(board.moves.len(), 5);
+        assert!(board.undo());
+        assert_eq!(board, before);
+        assert_eq!(board.movetext().unwrap(), "1. e4 d5 2. exd5 Nc6");
+
+        // a board set up by hand can't be replayed from the start
+        let mut board = castling_board();
+        board.apply_move((3, 7), (1, 7), None).unwrap();
+        assert_eq!(board.movetext(), None);
+        board.start();
+        assert_eq!(board.movetext().unwrap(), "");
+    }
+
+    #[test]
+    fn undo_restores_every_position() {
+        let moves = [
+            ("e2", "e4"),
+            ("d7", "d5"),
+            ("e4", "d5"),
+            ("e7", "e5"),
+            ("d5", "e6"),
+            ("e8", "e7"),
+            ("e6", "f7"),
+            ("d8", "e8"),
+            ("f7", "g8"),
+        ];
+        let mut board = Board::default();
+        let mut positions = Vec::new();
+        for (from, to) in moves {
+            positions.push(board.clone());
+            let (from, to) = (parse_square(from).unwrap(), parse_square(to).unwrap());
+            board.apply_move(from, to, None).unwrap();
+            let mut undone = board.clone();
+            assert!(undone.undo());
+            assert_eq!(&undone, positions.last().unwrap());
+        }
+        assert!(matches!(
+            board.get(&parse_square("g8").unwrap()),
+            Some(Piece::Queen(_))
+        ));
+
+        while let Some(position) = positions.pop() {
+            assert!(board.undo());
+            assert_eq!(board, position);
+        }
+        assert_eq!(board, Board::default());
+        assert!(!board.undo());
+    }
+
+    #[test]
+    fn undo_takes_back_the_clicks_since_the_move() {
+        let mut board = Board::default();
+        board
+            .apply_move(
+                parse_square("e2").unwrap(),
+                parse_square("e4").unwrap(),
+                None,
+            )
+            .unwrap();
+        assert!(!board.on_click(parse_square("e7").unwrap()));
+        assert!(board.on_click(parse_square("e5").unwrap()));
+        board.on_click(parse_square("g1").unwrap());
+        assert!(!board.move_squares.is_empty());
+
+        assert!(board.undo());
+        assert_eq!(board, Board::default());
+        assert!(!board.undo());
+    }
+
+    #[test]
+    fn perft_from_the_start() {
+        let board = Board::default();
+        assert_eq!(board.perft(1), 20);
+        assert_eq!(board.perft(2), 400);
+        assert_eq!(board.perft(3), 8902);
+
+        let divided = board.perft_divide(2);
+        assert_eq!(divided.len(), 20);
+        assert!(divided.iter().all(|(_, _, nodes)| *nodes == 20));
+    }
+
+    #[test]
+    fn perft_of_an_endgame_with_en_passant() {
+        // 8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -
+        let mut board = board_with(&[
+            ((5, 1), Piece::Pawn(Pawn::black())),
+            ((4, 2), Piece::Pawn(Pawn::black())),
+            ((7, 3), Piece::King(King::white())),
+            ((6, 3), Piece::Pawn(Pawn::white())),
+            ((0, 3), Piece::Rook(Rook::black())),
+            ((6, 4), Piece::Rook(Rook::white())),
+            ((2, 4), Piece::Pawn(Pawn::black())),
+            ((0, 4), Piece::King(King::black())),
+            ((3, 6), Piece::Pawn(Pawn::white())),
+            ((1, 6), Piece::Pawn(Pawn::white())),
+        ]);
+        board.castling_rights = 0;
+        assert_eq!(board.perft(0), 1);
+        assert_eq!(board.perft(1), 14);
+        assert_eq!(board.perft(2), 191);
+        assert_eq!(board.perft(3), 2812);
+    }
+
+    #[test]
+    fn perft_counts_every_promotion() {
+        let board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((0, 0), Piece::King(King::black())),
+            ((4, 1), Piece::Pawn(Pawn::white())),
+        ]);
+        // 3 king moves and 4 promotions
+        assert_eq!(board.perft(1), 7);
+        let promotion = board
+            .perft_divide(1)
+            .into_iter()
+            .find(|(from, _, _)| *from == (4, 1));
+        assert_eq!(promotion, Some(((4, 1), (4, 0), 4)));
+    }
+
+    fn sorted(mut squares: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        squares.sort();
+        squares
+    }
+
+    #[test]
+    fn attackers_on_the_starting_board() {
+        let board = Board::default();
+        assert_eq!(
+            sorted(board.attackers_of((3, 6), &Color::White)),
+            vec![(1, 7), (2, 7), (3, 7), (4, 7)]
+        );
+        assert_eq!(
+            sorted(board.attackers_of((3, 5), &Color::White)),
+            vec![(2, 6), (4, 6)]
+        );
+        assert!(board.attackers_of((3, 4), &Color::White).is_empty());
+        assert!(board.attackers_of((3, 3), &Color::Black).is_empty());
+        assert_eq!(
+            sorted(board.attackers_of((3, 2), &Color::Black)),
+            vec![(2, 1), (4, 1)]
+        );
+        // the d pawn is defended even though it can't be taken
+        assert_eq!(
+            sorted(board.attackers_of((3, 1), &Color::Black)),
+            vec![(1, 0), (2, 0), (3, 0), (4, 0)]
+        );
+    }
+
+    #[test]
+    fn repetitions_allow_claims_then_force_draws() {
+        let shuffle = [
+            ((6, 7), (5, 5)),
+            ((6, 0), (5, 2)),
+            ((5, 5), (6, 7)),
+            ((5, 2), (6, 0)),
+        ];
+        let mut board = Board::default();
+        for round in 1..=4 {
+            for mv in shuffle {
+                board = board.after_move(mv).unwrap();
+            }
+            assert_eq!(board.repetitions(), round + 1);
+            assert_eq!(board.can_claim_draw(), round >= 2);
+            assert_eq!(board.is_forced_draw(), round >= 4);
+            let state = match round {
+                1 => GameState::Ongoing,
+                2 | 3 => GameState::ThreefoldRepetition,
+                _ => GameState::ForcedDraw,
+            };
+            assert_eq!(board.game_state(), state);
+        }
+
+        // a pawn move makes the previous positions unreachable
+        let board = board.after_move(((4, 6), (4, 4))).unwrap();
+        assert_eq!(board.repetitions(), 1);
+        assert_eq!(board.halfmove_clock, 0);
+        assert!(!board.can_claim_draw());
+    }
+
+    #[test]
+    fn knight_shuffles_reach_threefold_repetition() {
+        let shuffle = [
+            ((1, 7), (2, 5)),
+            ((1, 0), (2, 2)),
+            ((2, 5), (1, 7)),
+            ((2, 2), (1, 0)),
+        ];
+        let mut board = Board::default();
+        for round in 1..=2 {
+            assert_eq!(board.game_state(), GameState::Ongoing);
+            for (from, to) in shuffle {
+                board.apply_move(from, to, None).unwrap();
+            }
+            assert_eq!(board.is_threefold_repetition(), round == 2);
+        }
+        assert_eq!(board.game_state(), GameState::ThreefoldRepetition);
+    }
+
+    #[test]
+    fn lost_castling_rights_make_a_new_position() {
+        let shuffle = [
+            ((3, 7), (4, 7)),
+            ((6, 2), (6, 3)),
+            ((4, 7), (3, 7)),
+            ((6, 3), (6, 2)),
+        ];
+        let mut board = castling_board();
+        let start = board.clone();
+        for (from, to) in shuffle {
+            board.apply_move(from, to, None).unwrap();
+        }
+        assert_ne!(board.zobrist_hash(), start.zobrist_hash());
+        // only the castling rights differ
+        let mut with_rights = board.clone();
+        with_rights.castling_rights = start.castling_rights;
+        assert_eq!(with_rights.zobrist_hash(), start.zobrist_hash());
+        assert_eq!(board.repetitions(), 1);
+        for (from, to) in shuffle.iter().chain(shuffle.iter()) {
+            board.apply_move(*from, *to, None).unwrap();
+        }
+        assert_eq!(board.repetitions(), 3);
+        assert!(board.is_threefold_repetition());
+    }
+
+    #[test]
+    fn fifty_and_seventy_five_move_rules() {
+        let mut board = Board {
+            halfmove_clock: 99,
+            ..Default::default()
+        };
+        assert!(!board.can_claim_draw());
+        board = board.after_move(((6, 7), (5, 5))).unwrap();
+        assert!(board.can_claim_draw());
+        assert!(!board.is_forced_draw());
+        assert_eq!(board.game_state(), GameState::Ongoing);
+
+        board.halfmove_clock = 150;
+        assert!(board.is_forced_draw());
+        assert_eq!(board.game_state(), GameState::ForcedDraw);
+    }
+
+    #[test]
+    fn fen_chars_round_trip() {
+        for c in "PNBRQKpnbrqk".chars() {
+            let piece = Piece::from_fen_char(c).unwrap();
+            assert_eq!(piece.to_fen_char(), c);
+            let color = match c.is_ascii_uppercase() {
+                true => Color::White,
+                false => Color::Black,
+            };
+            assert_eq!(piece.color(), &color);
+        }
+        assert_eq!(
+            Piece::from_fen_char('n'),
+            Some(Piece::Knight(Knight::black()))
+        );
+        assert_eq!(Piece::from_fen_char('x'), None);
+        assert_eq!(Piece::from_fen_char('1'), None);
+    }
+
+    #[test]
+    fn back_rank_mate_is_flagged() {
+        let board = board_with(&[
+            ((7, 7), Piece::King(King::white())),
+            ((0, 7), Piece::Rook(Rook::white())),
+            ((6, 0), Piece::King(King::black())),
+            ((5, 1), Piece::Pawn(Pawn::black())),
+            ((6, 1), Piece::Pawn(Pawn::black())),
+            ((7, 1), Piece::Pawn(Pawn::black())),
+        ]);
+
+        let moves = board.annotated_moves(&Color::White);
+        let (_, mate) = moves
+            .iter()
+            .find(|(mv, _)| *mv == ((0, 7), (0, 0)))
+            .unwrap();
+        assert!(mate.check && mate.checkmate);
+        assert!(!mate.capture && !mate.promotion && !mate.castle);
+
+        let (_, quiet) = moves
+            .iter()
+            .find(|(mv, _)| *mv == ((0, 7), (0, 3)))
+            .unwrap();
+        assert_eq!(*quiet, MoveFlags::default());
+        assert_eq!(moves.iter().filter(|(_, flags)| flags.checkmate).count(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+use chess_api::{Board, Color, GameState, MoveError, Piece};
+
+/// Files are mirrored on this board, the kings start on x = 3 and white is at the bottom.
+const SCHOLARS_MATE: [((usize, usize), (usize, usize)); 7] = [
+    ((3, 6), (3, 4)), // e4
+    ((3, 1), (3, 3)), // e5
+    ((2, 7), (5, 4)), // Bc4
+    ((6, 0), (5, 2)), // Nc6
+    ((4, 7), (0, 3)), // Qh5
+    ((1, 0), (2, 2)), // Nf6
+    ((0, 3), (2, 1)), // Qxf7#
+];
+
+#[test]
+fn scholars_mate() {
+    let mut board = Board::default();
+    for (from, to) in SCHOLARS_MATE {
+        let mover = board.turn.clone();
+        assert!(board
+            .legal_moves(&mover)
+            .contains(&(from.0, from.1, to.0, to.1)));
+        assert!(!board.is_check_mate(&mover));
+
+        board.apply_move(from, to, None).unwrap();
+        assert_eq!(board.turn, mover.other());
+        assert_eq!(board.get(&from), &None);
+        assert!(board.get(&to).as_ref().is_some_and(|p| p.color() == &mover));
+    }
+
+    // the queen took the f7 pawn, the bishop on c4 defends her
+    assert!(matches!(board.get(&(2, 1)), Some(Piece::Queen(_))));
+    assert!(matches!(board.get(&(5, 4)), Some(Piece::Bishop(_))));
+    assert_eq!(
+        board
+            .pieces
+            .iter()
+            .flatten()
+            .filter(|p| p.color() == &Color::Black)
+            .count(),
+        15
+    );
+    assert_eq!(board.game_state(), GameState::Checkmate(Color::White));
+    assert!(board.is_check(&Color::Black));
+    assert!(board.is_check_mate(&Color::Black));
+    assert!(board.legal_moves(&Color::Black).is_empty());
+    assert!(!board.is_stalemate(&Color::Black));
+    assert_eq!(
+        board.movetext().unwrap(),
+        "1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7#"
+    );
+}
+
+#[test]
+fn rejected_moves_leave_the_board_untouched() {
+    let mut board = Board::default();
+    let start = board.clone();
+    assert_eq!(
+        board.apply_move((3, 1), (3, 3), None),
+        Err(MoveError::NotYourTurn)
+    );
+    assert_eq!(
+        board.apply_move((3, 4), (3, 3), None),
+        Err(MoveError::NoPiece)
+    );
+    assert_eq!(
+        board.apply_move((3, 6), (3, 3), None),
+        Err(MoveError::IllegalMove)
+    );
+    assert_eq!(board, start);
+}
+
+#[test]
+fn en_passant_keeps_the_selection() {
+    let mut board = Board::default();
+    board.apply_move((3, 6), (3, 4), None).unwrap();
+    board.apply_move((7, 1), (7, 2), None).unwrap();
+    board.apply_move((3, 4), (3, 3), None).unwrap();
+    board.apply_move((4, 1), (4, 3), None).unwrap();
+    assert_eq!(board.en_passant_square, Some((4, 2)));
+
+    board.on_click((1, 7));
+    let move_squares = board.move_squares.clone();
+    assert!(!move_squares.is_empty());
+
+    board.apply_move((3, 3), (4, 2), None).unwrap();
+    assert!(matches!(board.get(&(4, 2)), Some(Piece::Pawn(_))));
+    assert_eq!(board.get(&(4, 3)), &None);
+    assert_eq!(board.en_passant_square, None);
+    assert_eq!(board.turn, Color::Black);
+    assert_eq!(board.piece_selected, Some((1, 7)));
+    assert_eq!(board.move_squares, move_squares);
+}
+
+#[test]
+fn stale_selection_cant_move_out_of_turn() {
+    let mut board = Board::default();
+    board.on_click((1, 7));
+    assert!(board.move_squares.contains(&(2, 5)));
+    board.apply_move((3, 6), (3, 4), None).unwrap();
+    assert!(!board.on_click((2, 5)));
+    assert!(matches!(board.get(&(1, 7)), Some(Piece::Knight(_))));
+
+    // even a selection restored by hand only moves the side to move
+    board.piece_selected = Some((1, 7));
+    board.move_squares.push((2, 5));
+    assert!(!board.on_click((2, 5)));
+    assert!(matches!(board.get(&(1, 7)), Some(Piece::Knight(_))));
+    assert_eq!(board.get(&(2, 5)), &None);
+    assert_eq!(board.turn, Color::Black);
+}